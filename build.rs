@@ -0,0 +1,28 @@
+//! Records the git commit and build date for `contacts version`, and passes
+//! a site-wide `CONTACTS_DATA_DIR` through to the compiler.
+use std::process::Command;
+
+fn main() {
+    let hash = Command::new("git")
+        .args(["rev-parse", "--short", "HEAD"])
+        .output()
+        .ok()
+        .filter(|out| out.status.success())
+        .and_then(|out| String::from_utf8(out.stdout).ok())
+        .map(|s| s.trim().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let date = chrono::Utc::now().format("%Y-%m-%d");
+
+    println!("cargo:rustc-env=CARGO_GIT_HASH={}", hash);
+    println!("cargo:rustc-env=CARGO_BUILD_DATE={}", date);
+    // Baked in as the default data directory (see `BUILD_DATA_DIR`).
+    println!("cargo:rerun-if-env-changed=CONTACTS_DATA_DIR");
+    if let Some(dir) = std::env::var("CONTACTS_DATA_DIR")
+        .ok()
+        .filter(|d| !d.is_empty())
+    {
+        println!("cargo:rustc-env=CONTACTS_DATA_DIR={}", dir);
+    }
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+}
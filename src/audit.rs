@@ -0,0 +1,151 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::store::{sidecar_path, Store};
+
+/// What kind of change an [`AuditEntry`] records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditOperation {
+    Add,
+    Remove,
+    Update,
+    Import,
+}
+
+impl fmt::Display for AuditOperation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            AuditOperation::Add => "add",
+            AuditOperation::Remove => "remove",
+            AuditOperation::Update => "update",
+            AuditOperation::Import => "import",
+        })
+    }
+}
+
+/// One line of the `<data file>.audit.jsonl` change log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: DateTime<Utc>,
+    pub operation: AuditOperation,
+    /// `None` for changes that touch many contacts, like imports.
+    pub contact_id: Option<String>,
+    pub summary: String,
+}
+
+impl fmt::Display for AuditEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:<6} {} {}",
+            self.timestamp.format("%Y-%m-%d %H:%M:%S"),
+            self.operation,
+            self.contact_id.as_deref().unwrap_or("-"),
+            self.summary
+        )
+    }
+}
+
+impl Store {
+    /// Note a change for the audit log. Entries are written by the next
+    /// successful [`Store::save`], so changes that are never saved are
+    /// never logged.
+    pub fn record_audit(
+        &mut self,
+        operation: AuditOperation,
+        contact_id: Option<&str>,
+        summary: impl Into<String>,
+    ) {
+        self.pending_audit.get_mut().push(AuditEntry {
+            timestamp: Utc::now(),
+            operation,
+            contact_id: contact_id.map(str::to_string),
+            summary: summary.into(),
+        });
+    }
+
+    /// Every entry in the audit log, oldest first. Empty if nothing has
+    /// been logged yet.
+    pub fn audit_log(&self) -> Result<Vec<AuditEntry>> {
+        let path = self.audit_path();
+        let text = match fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", path.display())),
+        };
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .enumerate()
+            .map(|(i, line)| {
+                serde_json::from_str(line)
+                    .with_context(|| format!("{} line {}", path.display(), i + 1))
+            })
+            .collect()
+    }
+
+    /// Append the pending entries to the audit log. The file is only ever
+    /// appended to, never rewritten.
+    pub(crate) fn flush_audit(&self) -> Result<()> {
+        let pending = self.pending_audit.take();
+        if pending.is_empty() || self.path.as_os_str().is_empty() {
+            return Ok(());
+        }
+        let mut lines = Vec::new();
+        for entry in &pending {
+            serde_json::to_writer(&mut lines, entry)?;
+            lines.push(b'\n');
+        }
+        let path = self.audit_path();
+        let mut opts = OpenOptions::new();
+        opts.append(true).create(true);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::OpenOptionsExt;
+            opts.mode(0o600);
+        }
+        opts.open(&path)
+            .and_then(|mut file| file.write_all(&lines))
+            .with_context(|| format!("appending to {}", path.display()))
+    }
+
+    fn audit_path(&self) -> PathBuf {
+        sidecar_path(&self.path, "audit.jsonl")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::contact::Contact;
+    use tempfile::tempdir;
+
+    #[test]
+    fn entries_are_appended_on_save() -> Result<()> {
+        let dir = tempdir()?;
+        let mut store = Store::new(dir.path().join("contacts.json"));
+        let c = Contact::new("Alice", "alice@x.com", None)?;
+        let id = c.id.clone();
+        store.add(c);
+        store.record_audit(AuditOperation::Add, Some(&id), "Alice");
+        assert!(store.audit_log()?.is_empty());
+        store.save()?;
+
+        store.remove(&id);
+        store.record_audit(AuditOperation::Remove, Some(&id), "Alice");
+        store.save()?;
+
+        let log = Store::open(dir.path().join("contacts.json"))?
+            .0
+            .audit_log()?;
+        let ops: Vec<AuditOperation> = log.iter().map(|e| e.operation).collect();
+        assert_eq!(ops, [AuditOperation::Add, AuditOperation::Remove]);
+        assert!(log.iter().all(|e| e.contact_id.as_deref() == Some(&id)));
+        Ok(())
+    }
+}
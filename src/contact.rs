@@ -0,0 +1,986 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Datelike, FixedOffset, NaiveDate, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Deserializer, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use uuid::Uuid;
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct Contact {
+    pub id: String,
+    pub name: String,
+    /// All email addresses; the first is the primary one used for display,
+    /// search and duplicate checks. Never empty for a valid contact.
+    /// Older files stored a single `"email": "..."` string, which still loads.
+    #[serde(alias = "email", deserialize_with = "one_or_many")]
+    pub emails: Vec<String>,
+    pub phone: Option<String>,
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// `None` for contacts saved before creation times were recorded.
+    #[serde(default)]
+    pub created_at: Option<DateTime<Utc>>,
+    /// Time of the last successful [`Contact::apply`] (or creation).
+    /// `None` for contacts saved before update times were recorded.
+    #[serde(default)]
+    pub updated_at: Option<DateTime<Utc>>,
+    #[serde(default)]
+    pub birthday: Option<NaiveDate>,
+    #[serde(default)]
+    pub anniversary: Option<NaiveDate>,
+    /// IANA timezone name, e.g. `"America/New_York"`.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Employer or organisation.
+    #[serde(default)]
+    pub company: Option<String>,
+    #[serde(default)]
+    pub address: Option<Address>,
+    /// Platform name (lowercase) -> handle, e.g. `"github" -> "alice"`.
+    #[serde(default)]
+    pub social_handles: BTreeMap<String, String>,
+    /// Archived contacts are hidden from `list` and `find` unless asked for.
+    #[serde(default)]
+    pub archived: bool,
+    /// When the contact was archived; `Store::open` purges contacts archived
+    /// longer ago than [`StoreOptions::tombstone_ttl_days`](crate::StoreOptions).
+    #[serde(default)]
+    pub archived_at: Option<DateTime<Utc>>,
+}
+
+/// Minimum outer width of [`Contact::display_card`], borders included.
+pub const CARD_WIDTH: usize = 36;
+
+/// Postal address; every part is optional so partial addresses round-trip.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct Address {
+    #[serde(default)]
+    pub street: Option<String>,
+    #[serde(default)]
+    pub city: Option<String>,
+    #[serde(default)]
+    pub state: Option<String>,
+    #[serde(default)]
+    pub postal_code: Option<String>,
+    #[serde(default)]
+    pub country: Option<String>,
+}
+
+impl Address {
+    fn parts(&self) -> [(&'static str, &Option<String>); 5] {
+        [
+            ("street", &self.street),
+            ("city", &self.city),
+            ("state", &self.state),
+            ("postal code", &self.postal_code),
+            ("country", &self.country),
+        ]
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parts().iter().all(|(_, v)| v.is_none())
+    }
+
+    fn validate(&self) -> Result<()> {
+        for (label, value) in self.parts() {
+            if value.as_ref().is_some_and(|v| v.len() > 200) {
+                return Err(anyhow!("{} too long (max 200 chars)", label));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// How [`Contact::new_with_id_format`] makes ids for new contacts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum IdFormat {
+    /// Random UUID v4 (36 characters).
+    #[default]
+    Uuid,
+    /// The first 8 hex digits of a random UUID; easier to type, but may
+    /// collide in large address books.
+    Short,
+    /// The first 16 hex digits of SHA-256(name + email), so the same person
+    /// always gets the same id.
+    Hash,
+}
+
+impl IdFormat {
+    pub fn generate(self, name: &str, email: &str) -> String {
+        match self {
+            IdFormat::Uuid => Uuid::new_v4().to_string(),
+            IdFormat::Short => Uuid::new_v4().simple().to_string()[..8].to_string(),
+            IdFormat::Hash => {
+                let digest = Sha256::digest(format!("{}{}", name, email).as_bytes());
+                format!("{:x}", digest)[..16].to_string()
+            }
+        }
+    }
+
+    /// True if `id` could have been made by one of the formats.
+    pub fn is_valid_id(id: &str) -> bool {
+        Uuid::parse_str(id).is_ok()
+            || (matches!(id.len(), 8 | 16) && id.bytes().all(|b| b.is_ascii_hexdigit()))
+    }
+}
+
+/// Field changes for an existing contact; `None` leaves a field untouched.
+#[derive(Debug, Clone, Default)]
+pub struct ContactUpdate {
+    pub name: Option<String>,
+    /// Replaces the primary email.
+    pub email: Option<String>,
+    /// Appended after the existing addresses (duplicates are ignored).
+    pub extra_emails: Vec<String>,
+    pub phone: Option<String>,
+    pub timezone: Option<String>,
+    pub company: Option<String>,
+    /// Replaces the whole address; an empty one clears it.
+    pub address: Option<Address>,
+    /// Added to (or replacing per platform) the existing handles.
+    pub social_handles: BTreeMap<String, String>,
+}
+
+impl Contact {
+    pub fn new(name: &str, email: &str, phone: Option<&str>) -> Result<Self> {
+        Self::new_with_id_format(name, email, phone, IdFormat::Uuid)
+    }
+
+    /// Like [`Contact::new`], with the id made according to `id_format`.
+    pub fn new_with_id_format(
+        name: &str,
+        email: &str,
+        phone: Option<&str>,
+        id_format: IdFormat,
+    ) -> Result<Self> {
+        // Input validation & length limits
+        if name.trim().is_empty() || email.trim().is_empty() {
+            return Err(anyhow!("name and email must be non-empty"));
+        }
+        let name = validate_name(name)?;
+        let email = validate_email(email)?;
+        let phone = phone.map(validate_phone).transpose()?;
+
+        let now = Utc::now();
+        Ok(Contact {
+            id: id_format.generate(&name, &email),
+            name,
+            emails: vec![email],
+            phone,
+            tags: Vec::new(),
+            created_at: Some(now),
+            updated_at: Some(now),
+            birthday: None,
+            anniversary: None,
+            timezone: None,
+            company: None,
+            address: None,
+            social_handles: BTreeMap::new(),
+            archived: false,
+            archived_at: None,
+        })
+    }
+
+    /// Like [`Contact::new`], keeping an existing id (e.g. when migrating
+    /// from another system). `id` must be a UUID v4; it is stored in the
+    /// usual lowercase hyphenated form.
+    pub fn new_with_id(name: &str, email: &str, phone: Option<&str>, id: &str) -> Result<Self> {
+        let uuid = Uuid::parse_str(id.trim())
+            .ok()
+            .filter(|u| u.get_version() == Some(uuid::Version::Random))
+            .ok_or_else(|| anyhow!("'{}' is not a valid UUID v4", id))?;
+        let mut contact = Self::new(name, email, phone)?;
+        contact.id = uuid.hyphenated().to_string();
+        Ok(contact)
+    }
+
+    /// The primary (first) email address.
+    pub fn primary_email(&self) -> &str {
+        self.emails.first().map(String::as_str).unwrap_or_default()
+    }
+
+    /// Re-run the input checks on a contact that did not come from `new`
+    /// (e.g. one read from an import file or produced by a JSON Patch).
+    pub fn validate(&self) -> Result<()> {
+        validate_name(&self.name)?;
+        if self.emails.is_empty() {
+            return Err(anyhow!("email must be non-empty"));
+        }
+        for e in &self.emails {
+            validate_email(e)?;
+        }
+        if let Some(p) = &self.phone {
+            validate_phone(p)?;
+        }
+        for t in &self.tags {
+            validate_tag(t)?;
+        }
+        if let Some(tz) = &self.timezone {
+            validate_timezone(tz)?;
+        }
+        if let Some(company) = &self.company {
+            validate_company(company)?;
+        }
+        if let Some(a) = &self.address {
+            a.validate()?;
+        }
+        for (p, h) in &self.social_handles {
+            validate_social(p, h)?;
+        }
+        Ok(())
+    }
+
+    /// Parse one contact from JSON, with the checks a data file gets: the
+    /// id must be a valid [`IdFormat`] id and every field must pass
+    /// [`Contact::validate`].
+    pub fn from_json(s: &str) -> Result<Contact> {
+        let c: Contact = serde_json::from_str(s).context("parsing contact JSON")?;
+        c.validate_with_id()?;
+        Ok(c)
+    }
+
+    /// [`Contact::validate`], and also require a valid [`IdFormat`] id. For
+    /// contacts that arrive with an id of their own, e.g. from an import or
+    /// a patch; `Store::open` would not load one with a made-up id.
+    pub fn validate_with_id(&self) -> Result<()> {
+        if !IdFormat::is_valid_id(&self.id) {
+            return Err(anyhow!("invalid contact id '{}'", self.id));
+        }
+        self.validate()
+    }
+
+    /// The contact as compact JSON, as read back by [`Contact::from_json`].
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("contacts always serialize")
+    }
+
+    /// Validate every field in `update` first, then apply them, so a bad value
+    /// never leaves the contact half-modified.
+    pub fn apply(&mut self, update: ContactUpdate) -> Result<()> {
+        let name = update.name.as_deref().map(validate_name).transpose()?;
+        let email = update.email.as_deref().map(validate_email).transpose()?;
+        let extra_emails = update
+            .extra_emails
+            .iter()
+            .map(|e| validate_email(e))
+            .collect::<Result<Vec<_>>>()?;
+        let phone = update.phone.as_deref().map(validate_phone).transpose()?;
+        let timezone = update
+            .timezone
+            .as_deref()
+            .map(validate_timezone)
+            .transpose()?;
+        let company = update
+            .company
+            .as_deref()
+            .map(validate_company)
+            .transpose()?;
+        if let Some(address) = &update.address {
+            address.validate()?;
+        }
+        let social = update
+            .social_handles
+            .iter()
+            .map(|(p, h)| validate_social(p, h))
+            .collect::<Result<Vec<_>>>()?;
+
+        if let Some(name) = name {
+            self.name = name;
+        }
+        if let Some(email) = email {
+            match self.emails.first_mut() {
+                Some(primary) => *primary = email,
+                None => self.emails.push(email),
+            }
+        }
+        for e in extra_emails {
+            if !self.emails.iter().any(|x| x.eq_ignore_ascii_case(&e)) {
+                self.emails.push(e);
+            }
+        }
+        if let Some(phone) = phone {
+            self.phone = Some(phone);
+        }
+        if let Some(timezone) = timezone {
+            self.timezone = Some(timezone);
+        }
+        if let Some(company) = company {
+            self.company = Some(company);
+        }
+        if let Some(address) = update.address {
+            self.address = (!address.is_empty()).then_some(address);
+        }
+        self.social_handles.extend(social);
+        self.updated_at = Some(Utc::now());
+        Ok(())
+    }
+
+    /// Current wall-clock time in the contact's timezone, if one is set.
+    pub fn local_time(&self) -> Option<DateTime<FixedOffset>> {
+        let tz: Tz = self.timezone.as_deref()?.parse().ok()?;
+        Some(Utc::now().with_timezone(&tz).fixed_offset())
+    }
+
+    /// Name, primary email, phone and company in a box drawn with Unicode
+    /// line characters. The box is [`CARD_WIDTH`] characters wide, or wider
+    /// if a line needs it; unset fields are left out.
+    pub fn display_card(&self) -> String {
+        let lines: Vec<&str> = [
+            Some(self.name.as_str()),
+            Some(self.primary_email()).filter(|e| !e.is_empty()),
+            self.phone.as_deref(),
+            self.company.as_deref(),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        let inner = lines
+            .iter()
+            .map(|l| l.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(CARD_WIDTH - 4);
+        let rule = "─".repeat(inner + 2);
+        let mut card = format!("┌{}┐\n", rule);
+        for line in lines {
+            card.push_str(&format!("│ {:<width$} │\n", line, width = inner));
+        }
+        card.push_str(&format!("└{}┘", rule));
+        card
+    }
+
+    /// A standalone schema.org `Person` in JSON-LD, with only the fields
+    /// that are set.
+    pub fn to_json_ld(&self) -> String {
+        let mut person = serde_json::json!({
+            "@context": "https://schema.org",
+            "@type": "Person",
+            "identifier": self.id,
+            "name": self.name,
+        });
+        let obj = person.as_object_mut().expect("json! object");
+        match self.emails.as_slice() {
+            [] => {}
+            [one] => {
+                obj.insert("email".into(), one.as_str().into());
+            }
+            many => {
+                obj.insert("email".into(), many.into());
+            }
+        }
+        if let Some(phone) = &self.phone {
+            obj.insert("telephone".into(), phone.as_str().into());
+        }
+        if let Some(birthday) = self.birthday {
+            obj.insert("birthDate".into(), birthday.to_string().into());
+        }
+        if let Some(company) = &self.company {
+            obj.insert(
+                "worksFor".into(),
+                serde_json::json!({ "@type": "Organization", "name": company }),
+            );
+        }
+        if !self.tags.is_empty() {
+            obj.insert("keywords".into(), self.tags.join(",").into());
+        }
+        if let Some(a) = self.address.as_ref().filter(|a| !a.is_empty()) {
+            let mut address = serde_json::Map::new();
+            address.insert("@type".into(), "PostalAddress".into());
+            let parts = [
+                ("streetAddress", &a.street),
+                ("addressLocality", &a.city),
+                ("addressRegion", &a.state),
+                ("postalCode", &a.postal_code),
+                ("addressCountry", &a.country),
+            ];
+            for (key, value) in parts {
+                if let Some(v) = value {
+                    address.insert(key.into(), v.as_str().into());
+                }
+            }
+            obj.insert("address".into(), address.into());
+        }
+        person.to_string()
+    }
+
+    /// Age in whole years today (UTC), or `None` without a birthday.
+    pub fn age(&self) -> Option<u32> {
+        self.age_on(Utc::now().date_naive())
+    }
+
+    /// Age in whole years on `today`; `None` without a birthday or if it is
+    /// in the future. A Feb 29 birthday only counts from Mar 1 in non-leap
+    /// years.
+    pub fn age_on(&self, today: NaiveDate) -> Option<u32> {
+        let birthday = self.birthday?;
+        if birthday > today {
+            return None;
+        }
+        let had_birthday = (today.month(), today.day()) >= (birthday.month(), birthday.day());
+        let years = today.year() - birthday.year() - i32::from(!had_birthday);
+        u32::try_from(years).ok()
+    }
+
+    /// True if the contact was created in `[since, before)`. Contacts without a
+    /// creation time only match when no bound is given.
+    pub fn created_between(
+        &self,
+        since: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> bool {
+        if since.is_none() && before.is_none() {
+            return true;
+        }
+        match self.created_at {
+            Some(t) => since.is_none_or(|s| t >= s) && before.is_none_or(|b| t < b),
+            None => false,
+        }
+    }
+
+    /// True if the contact carries every tag in `tags` (so also when `tags`
+    /// is empty). Tags compare exactly.
+    pub fn has_all_tags(&self, tags: &[&str]) -> bool {
+        tags.iter().all(|t| self.tags.iter().any(|own| own == t))
+    }
+
+    /// True if the contact carries at least one tag in `tags`.
+    pub fn has_any_tag(&self, tags: &[&str]) -> bool {
+        tags.iter().any(|t| self.tags.iter().any(|own| own == t))
+    }
+
+    /// Attach a tag (trimmed). Returns `false` if the contact already had it.
+    pub fn add_tag(&mut self, tag: &str) -> Result<bool> {
+        let tag = validate_tag(tag)?;
+        if self.tags.contains(&tag) {
+            return Ok(false);
+        }
+        self.tags.push(tag);
+        Ok(true)
+    }
+
+    /// Append the tags of `other` that this contact lacks, keeping both
+    /// orders. Tag lists are short, so a linear scan per tag is fine.
+    pub fn merge_tags(&mut self, other: &Contact) {
+        for tag in &other.tags {
+            if !self.tags.iter().any(|t| t == tag) {
+                self.tags.push(tag.clone());
+            }
+        }
+    }
+}
+
+/// Step-by-step construction of a [`Contact`], either from scratch or
+/// starting from an existing one. Nothing is checked until [`build`].
+///
+/// [`build`]: ContactBuilder::build
+#[derive(Debug, Clone)]
+pub struct ContactBuilder {
+    contact: Contact,
+    existing: bool,
+}
+
+impl ContactBuilder {
+    /// A new contact with a fresh id.
+    pub fn new(name: &str, email: &str) -> Self {
+        ContactBuilder {
+            contact: Contact {
+                id: Uuid::new_v4().to_string(),
+                name: name.trim().to_string(),
+                emails: vec![email.trim().to_string()],
+                phone: None,
+                tags: Vec::new(),
+                created_at: None,
+                updated_at: None,
+                birthday: None,
+                anniversary: None,
+                timezone: None,
+                company: None,
+                address: None,
+                social_handles: BTreeMap::new(),
+                archived: false,
+                archived_at: None,
+            },
+            existing: false,
+        }
+    }
+
+    /// Start from a copy of `c`; `build` keeps its id and `created_at`.
+    pub fn from_contact(c: &Contact) -> Self {
+        ContactBuilder {
+            contact: c.clone(),
+            existing: true,
+        }
+    }
+
+    pub fn name(mut self, name: &str) -> Self {
+        self.contact.name = name.trim().to_string();
+        self
+    }
+
+    /// Replace the primary email; other addresses are kept.
+    pub fn email(mut self, email: &str) -> Self {
+        let email = email.trim().to_string();
+        match self.contact.emails.first_mut() {
+            Some(primary) => *primary = email,
+            None => self.contact.emails.push(email),
+        }
+        self
+    }
+
+    pub fn phone(mut self, phone: Option<&str>) -> Self {
+        self.contact.phone = phone.map(|p| p.trim().to_string());
+        self
+    }
+
+    pub fn tag(mut self, tag: &str) -> Self {
+        let tag = tag.trim().to_string();
+        if !self.contact.tags.contains(&tag) {
+            self.contact.tags.push(tag);
+        }
+        self
+    }
+
+    pub fn birthday(mut self, birthday: Option<NaiveDate>) -> Self {
+        self.contact.birthday = birthday;
+        self
+    }
+
+    pub fn timezone(mut self, timezone: Option<&str>) -> Self {
+        self.contact.timezone = timezone.map(|t| t.trim().to_string());
+        self
+    }
+
+    pub fn company(mut self, company: Option<&str>) -> Self {
+        self.contact.company = company.map(|c| c.trim().to_string());
+        self
+    }
+
+    pub fn address(mut self, address: Option<Address>) -> Self {
+        self.contact.address = address.filter(|a| !a.is_empty());
+        self
+    }
+
+    /// Validate every field and return the contact with `updated_at` set to
+    /// now (and `created_at` too, for a new contact).
+    pub fn build(self) -> Result<Contact> {
+        let mut contact = self.contact;
+        contact.validate()?;
+        let now = Utc::now();
+        if !self.existing {
+            contact.created_at = Some(now);
+        }
+        contact.updated_at = Some(now);
+        Ok(contact)
+    }
+}
+
+/// Accept either a single string (the old `"email"` format) or a list.
+fn one_or_many<'de, D: Deserializer<'de>>(d: D) -> Result<Vec<String>, D::Error> {
+    // Binary formats can't guess the shape; they always hold the list.
+    if !d.is_human_readable() {
+        return Vec::deserialize(d);
+    }
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum OneOrMany {
+        One(String),
+        Many(Vec<String>),
+    }
+    Ok(match OneOrMany::deserialize(d)? {
+        OneOrMany::One(s) => vec![s],
+        OneOrMany::Many(v) => v,
+    })
+}
+
+fn validate_name(name: &str) -> Result<String> {
+    if name.trim().is_empty() {
+        return Err(anyhow!("name must be non-empty"));
+    }
+    if name.len() > 200 {
+        return Err(anyhow!("name too long (max 200 chars)"));
+    }
+    Ok(name.trim().to_string())
+}
+
+fn validate_company(company: &str) -> Result<String> {
+    if company.len() > 200 {
+        return Err(anyhow!("company too long (max 200 chars)"));
+    }
+    Ok(company.trim().to_string())
+}
+
+fn validate_email(email: &str) -> Result<String> {
+    if email.trim().is_empty() {
+        return Err(anyhow!("email must be non-empty"));
+    }
+    if email.len() > 320 {
+        return Err(anyhow!("email too long (max 320 chars)"));
+    }
+    if email.chars().any(char::is_control) {
+        return Err(anyhow!("email must not contain control characters"));
+    }
+    Ok(email.trim().to_string())
+}
+
+fn validate_phone(phone: &str) -> Result<String> {
+    if phone.len() > 50 {
+        return Err(anyhow!("phone too long (max 50 chars)"));
+    }
+    Ok(phone.trim().to_string())
+}
+
+/// Only accept names from the IANA database, so `local_time` always works.
+fn validate_timezone(tz: &str) -> Result<String> {
+    let tz = tz.trim();
+    if tz.len() > 64 {
+        return Err(anyhow!("timezone too long (max 64 chars)"));
+    }
+    tz.parse::<Tz>().map_err(|_| {
+        anyhow!(
+            "unknown timezone '{}' (expected an IANA name like Europe/London)",
+            tz
+        )
+    })?;
+    Ok(tz.to_string())
+}
+
+/// Platforms are matched case-insensitively, so they are stored lowercase.
+fn validate_social(platform: &str, handle: &str) -> Result<(String, String)> {
+    let platform = platform.trim().to_lowercase();
+    let handle = handle.trim();
+    if platform.is_empty() || handle.is_empty() {
+        return Err(anyhow!("social platform and handle must be non-empty"));
+    }
+    if platform.len() > 30 {
+        return Err(anyhow!("social platform too long (max 30 chars)"));
+    }
+    if handle.len() > 200 {
+        return Err(anyhow!("social handle too long (max 200 chars)"));
+    }
+    Ok((platform, handle.to_string()))
+}
+
+/// Tags are short free-form labels; reject empty or oversized ones.
+pub(crate) fn validate_tag(tag: &str) -> Result<String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        return Err(anyhow!("tag must be non-empty"));
+    }
+    if tag.len() > 50 {
+        return Err(anyhow!("tag too long (max 50 chars)"));
+    }
+    Ok(tag.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn contact_validation() {
+        assert!(Contact::new("", "a@b.com", None).is_err());
+        assert!(Contact::new("Alice", "", None).is_err());
+        let long_name = "x".repeat(201);
+        assert!(Contact::new(&long_name, "a@b.com", None).is_err());
+        let ok = Contact::new("Alice", "a@b.com", Some("1234")).unwrap();
+        assert_eq!(ok.name, "Alice");
+    }
+
+    #[test]
+    fn age_handles_leap_day_and_missing_birthday() -> Result<()> {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let mut c = Contact::new("Alice", "a@b.com", None)?;
+        assert_eq!(c.age_on(date(2025, 6, 1)), None);
+
+        c.birthday = Some(date(2000, 2, 29));
+        assert_eq!(c.age_on(date(2023, 2, 28)), Some(22));
+        assert_eq!(c.age_on(date(2023, 3, 1)), Some(23));
+        assert_eq!(c.age_on(date(2024, 2, 29)), Some(24));
+        assert_eq!(c.age_on(date(2000, 2, 29)), Some(0));
+        assert_eq!(c.age_on(date(1999, 12, 31)), None);
+        Ok(())
+    }
+
+    #[test]
+    fn json_ld_has_person_type_and_set_fields() -> Result<()> {
+        let mut c = Contact::new("Alice", "alice@x.com", Some("555"))?;
+        let ld: serde_json::Value = serde_json::from_str(&c.to_json_ld())?;
+        assert_eq!(ld["@type"], "Person");
+        assert_eq!(ld["@context"], "https://schema.org");
+        assert!(ld.get("birthDate").is_none());
+        assert!(ld.get("address").is_none());
+
+        c.emails.push("a@work.com".into());
+        c.birthday = NaiveDate::from_ymd_opt(1990, 5, 1);
+        c.tags = vec!["work".into(), "friend".into()];
+        c.address = Some(Address {
+            city: Some("Oslo".into()),
+            country: Some("Norway".into()),
+            ..Default::default()
+        });
+        let ld: serde_json::Value = serde_json::from_str(&c.to_json_ld())?;
+        assert_eq!(ld["name"], "Alice");
+        assert_eq!(
+            ld["email"],
+            serde_json::json!(["alice@x.com", "a@work.com"])
+        );
+        assert_eq!(ld["telephone"], "555");
+        assert_eq!(ld["birthDate"], "1990-05-01");
+        assert_eq!(ld["keywords"], "work,friend");
+        assert_eq!(ld["address"]["@type"], "PostalAddress");
+        assert_eq!(ld["address"]["addressLocality"], "Oslo");
+        assert_eq!(ld["address"]["addressCountry"], "Norway");
+        assert!(ld["address"].get("streetAddress").is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn display_card_box_dimensions() -> Result<()> {
+        let mut c = Contact::new("Alice Smith", "alice@example.com", Some("+1-555-0100"))?;
+        c.company = Some("Acme Corp".to_string());
+        let card = c.display_card();
+        let lines: Vec<&str> = card.lines().collect();
+        assert_eq!(lines.len(), 6);
+        assert_eq!(lines[1], format!("│ {:<32} │", "Alice Smith"));
+        assert_eq!(lines[4], format!("│ {:<32} │", "Acme Corp"));
+        assert!(lines.iter().all(|l| l.chars().count() == CARD_WIDTH));
+        assert!(lines[0].starts_with('┌') && lines[5].ends_with('┘'));
+
+        // A long line widens the whole box; missing fields are skipped.
+        let long = "x".repeat(50);
+        let c = Contact::new(&long, "b@x.com", None)?;
+        let card = c.display_card();
+        let lines: Vec<&str> = card.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|l| l.chars().count() == 54));
+        Ok(())
+    }
+
+    #[test]
+    fn builder_from_contact_keeps_other_fields() -> Result<()> {
+        let mut original = Contact::new("Alice", "alice@x.com", Some("123"))?;
+        original.add_tag("work")?;
+        original.birthday = NaiveDate::from_ymd_opt(1990, 5, 1);
+        original.company = Some("Acme".to_string());
+
+        let built = ContactBuilder::from_contact(&original)
+            .email("alice@new.com")
+            .build()?;
+        assert_eq!(built.id, original.id);
+        assert_eq!(built.created_at, original.created_at);
+        assert_eq!(built.primary_email(), "alice@new.com");
+        assert_ne!(built.updated_at, original.updated_at);
+
+        let mut expected = original.clone();
+        expected.emails[0] = "alice@new.com".to_string();
+        expected.updated_at = built.updated_at;
+        assert_eq!(built, expected);
+
+        let fresh = ContactBuilder::new("Bob", "bob@x.com").tag("vip").build()?;
+        assert_ne!(fresh.id, original.id);
+        assert_eq!(fresh.created_at, fresh.updated_at);
+        assert!(ContactBuilder::new("", "x@x.com").build().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn id_formats() -> Result<()> {
+        let short = Contact::new_with_id_format("Alice", "a@x.com", None, IdFormat::Short)?;
+        assert_eq!(short.id.len(), 8);
+        assert!(IdFormat::is_valid_id(&short.id));
+
+        let hash = |name| -> Result<String> {
+            Ok(Contact::new_with_id_format(name, "a@x.com", None, IdFormat::Hash)?.id)
+        };
+        assert_eq!(hash("Alice")?, hash("Alice")?);
+        assert_ne!(hash("Alice")?, hash("Bob")?);
+        assert_eq!(hash("Alice")?.len(), 16);
+
+        assert_eq!(Contact::new("Alice", "a@x.com", None)?.id.len(), 36);
+        assert!(!IdFormat::is_valid_id("not-a-uuid"));
+        Ok(())
+    }
+
+    #[test]
+    fn merge_tags_is_an_ordered_union() -> Result<()> {
+        let mut a = Contact::new("A", "a@x.com", None)?;
+        a.tags = vec!["work".into(), "golf".into(), "vip".into()];
+        let mut b = Contact::new("B", "b@x.com", None)?;
+        b.tags = vec!["vip".into(), "family".into(), "work".into()];
+        a.merge_tags(&b);
+        assert_eq!(a.tags, ["work", "golf", "vip", "family"]);
+        Ok(())
+    }
+
+    #[test]
+    fn json_round_trip() -> Result<()> {
+        let mut c = Contact::new("Alice", "alice@x.com", Some("555-0100"))?;
+        c.add_tag("work")?;
+        c.company = Some("Acme".into());
+        let json = c.to_json();
+        assert!(!json.contains('\n'));
+        assert_eq!(Contact::from_json(&json)?, c);
+
+        c.id = "not-an-id".into();
+        assert!(Contact::from_json(&c.to_json()).is_err());
+        let long_name = Contact {
+            name: "x".repeat(500),
+            ..Contact::new("Bob", "bob@x.com", None)?
+        };
+        assert!(Contact::from_json(&long_name.to_json()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn new_with_id_requires_uuid_v4() -> Result<()> {
+        let id = "3F2A9C1E-7B4D-4E2A-9F1C-0D8E6B5A4C3B";
+        let c = Contact::new_with_id("Alice", "alice@x.com", None, id)?;
+        assert_eq!(c.id, id.to_lowercase());
+        // Version 1 and a short id are rejected.
+        assert!(
+            Contact::new_with_id("A", "a@x.com", None, "3f2a9c1e-7b4d-11e2-9f1c-0d8e6b5a4c3b")
+                .is_err()
+        );
+        assert!(Contact::new_with_id("A", "a@x.com", None, "3f2a9c1e").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn builder_trims_timezone() -> Result<()> {
+        let c = ContactBuilder::new("Alice", "a@b.com")
+            .timezone(Some("  Europe/London \n"))
+            .build()?;
+        assert_eq!(c.timezone.as_deref(), Some("Europe/London"));
+        assert!(c.local_time().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn timezone_is_validated() -> Result<()> {
+        let mut c = Contact::new("Alice", "a@b.com", None)?;
+        let tz = |s: &str| ContactUpdate {
+            timezone: Some(s.to_string()),
+            ..Default::default()
+        };
+        assert!(c.apply(tz("Mars/Olympus_Mons")).is_err());
+        assert!(c.apply(tz(&"A".repeat(65))).is_err());
+        assert_eq!(c.timezone, None);
+        assert!(c.local_time().is_none());
+
+        c.apply(tz("America/New_York"))?;
+        assert_eq!(c.timezone.as_deref(), Some("America/New_York"));
+        assert!(c.local_time().is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn apply_is_all_or_nothing() -> Result<()> {
+        let mut c = Contact::new("Alice", "a@b.com", None)?;
+        let bad = ContactUpdate {
+            name: Some("Alicia".into()),
+            phone: Some("9".repeat(51)),
+            ..Default::default()
+        };
+        assert!(c.apply(bad).is_err());
+        assert_eq!(c.name, "Alice");
+        Ok(())
+    }
+
+    #[test]
+    fn address_round_trips_including_partial() -> Result<()> {
+        let mut c = Contact::new("Alice", "a@b.com", None)?;
+        c.apply(ContactUpdate {
+            address: Some(Address {
+                street: Some("1 Main St".into()),
+                city: Some("Springfield".into()),
+                state: Some("IL".into()),
+                postal_code: Some("62701".into()),
+                country: Some("US".into()),
+            }),
+            ..Default::default()
+        })?;
+        let json = serde_json::to_string(&c)?;
+        let back: Contact = serde_json::from_str(&json)?;
+        let addr = back.address.unwrap();
+        assert_eq!(addr.street.as_deref(), Some("1 Main St"));
+        assert_eq!(addr.postal_code.as_deref(), Some("62701"));
+
+        let partial: Address = serde_json::from_str(r#"{"city": "Paris"}"#)?;
+        assert_eq!(partial.city.as_deref(), Some("Paris"));
+        assert!(partial.street.is_none() && partial.country.is_none());
+        let json = serde_json::to_string(&partial)?;
+        let again: Address = serde_json::from_str(&json)?;
+        assert_eq!(again.city.as_deref(), Some("Paris"));
+        Ok(())
+    }
+
+    #[test]
+    fn social_handles_are_validated() -> Result<()> {
+        let mut c = Contact::new("Alice", "a@b.com", None)?;
+        let social = |p: &str, h: &str| ContactUpdate {
+            social_handles: BTreeMap::from([(p.to_string(), h.to_string())]),
+            ..Default::default()
+        };
+        assert!(c.apply(social(&"p".repeat(31), "alice")).is_err());
+        assert!(c.apply(social("github", &"h".repeat(201))).is_err());
+        c.apply(social("GitHub", "alice"))?;
+        c.apply(social("twitter", "@alice"))?;
+        assert_eq!(
+            c.social_handles.get("github").map(String::as_str),
+            Some("alice")
+        );
+        assert_eq!(c.social_handles.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn legacy_single_email_round_trips() -> Result<()> {
+        let old = r#"{
+            "id": "ddeab3cc-4256-4596-bff7-bc9d940e195f",
+            "name": "Charlie",
+            "email": "charlie@example.com",
+            "phone": null
+        }"#;
+        let c: Contact = serde_json::from_str(old)?;
+        assert_eq!(c.emails, ["charlie@example.com"]);
+        assert_eq!(c.primary_email(), "charlie@example.com");
+
+        let json = serde_json::to_value(&c)?;
+        assert!(json.get("email").is_none());
+        let back: Contact = serde_json::from_value(json)?;
+        assert_eq!(back, c);
+        Ok(())
+    }
+
+    #[test]
+    fn primary_email_is_first() -> Result<()> {
+        let mut c = Contact::new("Alice", "alice@home.com", None)?;
+        c.apply(ContactUpdate {
+            extra_emails: vec!["alice@work.com".into(), "ALICE@home.com".into()],
+            ..Default::default()
+        })?;
+        assert_eq!(c.emails, ["alice@home.com", "alice@work.com"]);
+        assert_eq!(c.primary_email(), "alice@home.com");
+
+        c.apply(ContactUpdate {
+            email: Some("alice@new.com".into()),
+            ..Default::default()
+        })?;
+        assert_eq!(c.emails, ["alice@new.com", "alice@work.com"]);
+        assert_eq!(c.primary_email(), "alice@new.com");
+        Ok(())
+    }
+
+    #[test]
+    fn tags_are_validated_and_deduplicated() -> Result<()> {
+        let mut c = Contact::new("Alice", "a@b.com", None)?;
+        assert!(c.add_tag(" work ")?);
+        assert!(!c.add_tag("work")?);
+        assert!(c.add_tag("").is_err());
+        assert!(c.add_tag(&"t".repeat(51)).is_err());
+        assert_eq!(c.tags, vec!["work"]);
+        Ok(())
+    }
+}
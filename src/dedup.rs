@@ -0,0 +1,293 @@
+use crate::contact::Contact;
+use crate::store::Store;
+
+/// How [`Store::find_duplicates`] decides that two contacts are the same person.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DedupStrategy {
+    /// Primary emails are equal, ignoring case and surrounding whitespace.
+    ExactEmail,
+    /// [`Contact::similarity_score`] is at least `min_percent` / 100. Names
+    /// carry the most weight, but email, phone and company count too.
+    FuzzyName { min_percent: u8 },
+}
+
+/// Why a [`DuplicatePair`] was reported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DuplicateReason {
+    SameEmail,
+    /// [`Contact::similarity_score`], rounded down to a whole percent.
+    SimilarName {
+        percent: u8,
+    },
+}
+
+/// Two contacts that look like duplicates, as indices into [`Store::contacts`].
+/// `a` is always smaller than `b`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DuplicatePair {
+    pub a: usize,
+    pub b: usize,
+    pub reason: DuplicateReason,
+}
+
+impl Store {
+    /// Return every pair of contacts considered duplicates under `strategy`.
+    ///
+    /// This compares all pairs (O(n²)).
+    pub fn find_duplicates(&self, strategy: DedupStrategy) -> Vec<DuplicatePair> {
+        let contacts = self.contacts();
+        let mut pairs = Vec::new();
+
+        match strategy {
+            DedupStrategy::ExactEmail => {
+                let emails: Vec<String> = contacts
+                    .iter()
+                    .map(|c| c.primary_email().trim().to_lowercase())
+                    .collect();
+                for a in 0..emails.len() {
+                    for b in (a + 1)..emails.len() {
+                        if emails[a] == emails[b] {
+                            pairs.push(DuplicatePair {
+                                a,
+                                b,
+                                reason: DuplicateReason::SameEmail,
+                            });
+                        }
+                    }
+                }
+            }
+            DedupStrategy::FuzzyName { min_percent } => {
+                let min = f64::from(min_percent) / 100.0;
+                for a in 0..contacts.len() {
+                    for b in (a + 1)..contacts.len() {
+                        let score = contacts[a].similarity_score(&contacts[b]);
+                        if score >= min {
+                            pairs.push(DuplicatePair {
+                                a,
+                                b,
+                                reason: DuplicateReason::SimilarName {
+                                    percent: (score * 100.0) as u8,
+                                },
+                            });
+                        }
+                    }
+                }
+            }
+        }
+
+        pairs
+    }
+}
+
+impl Contact {
+    /// How alike two contacts are, from 0.0 (nothing in common) to 1.0.
+    /// A weighted mean of Jaro-Winkler similarity of the names (0.4), edit
+    /// similarity of the email local parts (0.3) and of the phone digits
+    /// (0.2), and an exact, case-insensitive company match (0.1). Phone and
+    /// company only count when at least one contact has them, so two
+    /// contacts that both lack a phone are neither alike nor different there.
+    pub fn similarity_score(&self, other: &Contact) -> f64 {
+        let local = |c: &Contact| -> Vec<char> {
+            let email = c.primary_email().trim().to_lowercase();
+            let local = email.split('@').next().unwrap_or_default();
+            local.chars().collect()
+        };
+        let digits = |c: &Contact| -> Option<Vec<char>> {
+            c.phone
+                .as_ref()
+                .map(|p| p.chars().filter(char::is_ascii_digit).collect())
+        };
+        let company = |c: &Contact| c.company.as_ref().map(|s| s.trim().to_lowercase());
+
+        let mut parts = vec![
+            (
+                0.4,
+                jaro_winkler(&normalized_name(self), &normalized_name(other)),
+            ),
+            (0.3, edit_similarity(&local(self), &local(other))),
+        ];
+        match (digits(self), digits(other)) {
+            (None, None) => {}
+            (Some(a), Some(b)) => parts.push((0.2, edit_similarity(&a, &b))),
+            _ => parts.push((0.2, 0.0)),
+        }
+        match (company(self), company(other)) {
+            (None, None) => {}
+            (a, b) => parts.push((0.1, if a == b { 1.0 } else { 0.0 })),
+        }
+        let weight: f64 = parts.iter().map(|(w, _)| w).sum();
+        parts.iter().map(|(w, s)| w * s).sum::<f64>() / weight
+    }
+}
+
+/// Lowercased name tokens joined by single spaces, so that case and extra
+/// whitespace never count as edits.
+fn normalized_name(c: &Contact) -> Vec<char> {
+    c.name
+        .split_whitespace()
+        .map(str::to_lowercase)
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .collect()
+}
+
+/// 1.0 minus the edit distance as a share of the longer string; two empty
+/// strings are identical.
+fn edit_similarity(a: &[char], b: &[char]) -> f64 {
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+/// Jaro similarity, boosted by 0.1 per shared leading character (up to
+/// four) as proposed by Winkler.
+fn jaro_winkler(a: &[char], b: &[char]) -> f64 {
+    let jaro = jaro(a, b);
+    let prefix = a.iter().zip(b).take(4).take_while(|(x, y)| x == y).count();
+    jaro + prefix as f64 * 0.1 * (1.0 - jaro)
+}
+
+fn jaro(a: &[char], b: &[char]) -> f64 {
+    if a.is_empty() && b.is_empty() {
+        return 1.0;
+    }
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    // Characters match if equal and no further apart than this.
+    let window = (a.len().max(b.len()) / 2).saturating_sub(1);
+    let mut a_matched = vec![false; a.len()];
+    let mut b_matched = vec![false; b.len()];
+    let mut matches = 0;
+    for (i, ca) in a.iter().enumerate() {
+        let start = i.saturating_sub(window);
+        let end = (i + window + 1).min(b.len());
+        for j in start..end {
+            if !b_matched[j] && b[j] == *ca {
+                a_matched[i] = true;
+                b_matched[j] = true;
+                matches += 1;
+                break;
+            }
+        }
+    }
+    if matches == 0 {
+        return 0.0;
+    }
+    // Half the number of matched characters that appear in a different order.
+    let a_seq = a.iter().zip(&a_matched).filter(|(_, &m)| m).map(|(c, _)| c);
+    let b_seq = b.iter().zip(&b_matched).filter(|(_, &m)| m).map(|(c, _)| c);
+    let transpositions = a_seq.zip(b_seq).filter(|(x, y)| x != y).count() / 2;
+
+    let m = matches as f64;
+    (m / a.len() as f64 + m / b.len() as f64 + (m - transpositions as f64) / m) / 3.0
+}
+
+/// Classic two-row Levenshtein edit distance.
+fn levenshtein(a: &[char], b: &[char]) -> usize {
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    let mut cur = vec![0; b.len() + 1];
+    for (i, ca) in a.iter().enumerate() {
+        cur[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = usize::from(ca != cb);
+            cur[j + 1] = (prev[j] + cost).min(prev[j + 1] + 1).min(cur[j] + 1);
+        }
+        std::mem::swap(&mut prev, &mut cur);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn levenshtein_basics() {
+        let d = |a: &str, b: &str| {
+            levenshtein(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert_eq!(d("", ""), 0);
+        assert_eq!(d("abc", ""), 3);
+        assert_eq!(d("kitten", "sitting"), 3);
+    }
+
+    #[test]
+    fn jaro_winkler_known_values() {
+        let jw = |a: &str, b: &str| {
+            jaro_winkler(
+                &a.chars().collect::<Vec<_>>(),
+                &b.chars().collect::<Vec<_>>(),
+            )
+        };
+        assert!((jw("martha", "marhta") - 0.9611).abs() < 1e-4);
+        assert!((jw("dwayne", "duane") - 0.84).abs() < 1e-4);
+        assert_eq!(jw("abc", "abc"), 1.0);
+        assert_eq!(jw("abc", "xyz"), 0.0);
+    }
+
+    #[test]
+    fn similarity_score_bounds() -> Result<()> {
+        let mut alice = Contact::new("Alice Smith", "alice@x.com", Some("555-0100"))?;
+        alice.company = Some("Acme".into());
+        assert_eq!(alice.similarity_score(&alice.clone()), 1.0);
+
+        let mut other = Contact::new("Xu", "bo@y.org", Some("999-7777"))?;
+        other.company = Some("Globex".into());
+        assert!(alice.similarity_score(&other) <= 0.1);
+
+        let typo = Contact::new("Alice Smyth", "alice.smith@y.org", Some("(555) 0100"))?;
+        let score = alice.similarity_score(&typo);
+        assert!(score > 0.5 && score < 1.0);
+
+        let mut store = Store::default();
+        store.add(alice);
+        store.add(other);
+        store.add(typo);
+        Ok(())
+    }
+
+    #[test]
+    fn fuzzy_name_flags_one_typo() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice Smith", "alice@x.com", None)?);
+        store.add(Contact::new("Bob Brown", "bob@x.com", None)?);
+        store.add(Contact::new("Alise  smith", "alise@y.com", None)?);
+
+        let pairs = store.find_duplicates(DedupStrategy::FuzzyName { min_percent: 80 });
+        assert_eq!(
+            pairs,
+            vec![DuplicatePair {
+                a: 0,
+                b: 2,
+                reason: DuplicateReason::SimilarName { percent: 87 },
+            }]
+        );
+        assert!(store
+            .find_duplicates(DedupStrategy::FuzzyName { min_percent: 90 })
+            .is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn exact_email_returns_all_pairs() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("A", "same@x.com", None)?);
+        store.add(Contact::new("B", "SAME@x.com", None)?);
+        store.add(Contact::new("C", "same@x.com", None)?);
+        store.add(Contact::new("D", "other@x.com", None)?);
+
+        let pairs = store.find_duplicates(DedupStrategy::ExactEmail);
+        let idx: Vec<(usize, usize)> = pairs.iter().map(|p| (p.a, p.b)).collect();
+        assert_eq!(idx, vec![(0, 1), (0, 2), (1, 2)]);
+        assert!(pairs.iter().all(|p| p.reason == DuplicateReason::SameEmail));
+        Ok(())
+    }
+}
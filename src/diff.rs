@@ -0,0 +1,142 @@
+use serde_json::Value;
+use std::fmt;
+
+use crate::contact::Contact;
+use crate::store::Store;
+
+/// One field whose value differs between two versions of a contact.
+/// Values are rendered as compact JSON.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FieldChange {
+    pub field: String,
+    pub old: String,
+    pub new: String,
+}
+
+/// A contact present in both stores (matched by id) but not equal.
+#[derive(Debug, Clone)]
+pub struct ContactChange<'a> {
+    pub contact: &'a Contact,
+    pub fields: Vec<FieldChange>,
+}
+
+/// Result of [`Store::diff_with`].
+#[derive(Debug, Default)]
+pub struct StoreDiff<'a> {
+    pub added: Vec<&'a Contact>,
+    pub removed: Vec<&'a Contact>,
+    pub changed: Vec<ContactChange<'a>>,
+}
+
+impl StoreDiff<'_> {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+impl Store {
+    /// What changed going from `other` to `self`: `added` are only in `self`,
+    /// `removed` only in `other`, and `changed` lists `other` -> `self` values.
+    pub fn diff_with<'a>(&'a self, other: &'a Store) -> StoreDiff<'a> {
+        let mut diff = StoreDiff::default();
+        for c in self.contacts() {
+            match other.find_by_id(&c.id) {
+                None => diff.added.push(c),
+                Some(old) if old != c => diff.changed.push(ContactChange {
+                    contact: c,
+                    fields: field_changes(old, c),
+                }),
+                Some(_) => {}
+            }
+        }
+        diff.removed = other
+            .contacts()
+            .iter()
+            .filter(|c| self.find_by_id(&c.id).is_none())
+            .collect();
+        diff
+    }
+}
+
+fn field_changes(old: &Contact, new: &Contact) -> Vec<FieldChange> {
+    let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+        (serde_json::to_value(old), serde_json::to_value(new))
+    else {
+        return Vec::new();
+    };
+    new.iter()
+        .filter_map(|(field, new_value)| {
+            let old_value = old.get(field).unwrap_or(&Value::Null);
+            (old_value != new_value).then(|| FieldChange {
+                field: field.clone(),
+                old: old_value.to_string(),
+                new: new_value.to_string(),
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for StoreDiff<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Added:")?;
+        for c in &self.added {
+            writeln!(f, "  {} ({})", c.name, c.id)?;
+        }
+        writeln!(f, "Removed:")?;
+        for c in &self.removed {
+            writeln!(f, "  {} ({})", c.name, c.id)?;
+        }
+        writeln!(f, "Changed:")?;
+        for change in &self.changed {
+            writeln!(f, "  {} ({})", change.contact.name, change.contact.id)?;
+            for fc in &change.fields {
+                writeln!(f, "    {}: {} → {}", fc.field, fc.old, fc.new)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    #[test]
+    fn diff_reports_added_removed_changed() -> Result<()> {
+        let shared = Contact::new("Alice", "alice@x.com", None)?;
+        let only_old = Contact::new("Bob", "bob@x.com", None)?;
+        let only_new = Contact::new("Carol", "carol@x.com", None)?;
+
+        let mut old = Store::default();
+        old.add(shared.clone());
+        old.add(only_old.clone());
+
+        let mut modified = shared.clone();
+        modified.emails = vec!["alice@y.com".into()];
+        let mut new = Store::default();
+        new.add(modified);
+        new.add(only_new.clone());
+
+        let diff = new.diff_with(&old);
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].id, only_new.id);
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].id, only_old.id);
+        assert_eq!(diff.changed.len(), 1);
+        assert_eq!(
+            diff.changed[0].fields,
+            vec![FieldChange {
+                field: "emails".into(),
+                old: r#"["alice@x.com"]"#.into(),
+                new: r#"["alice@y.com"]"#.into(),
+            }]
+        );
+
+        let text = diff.to_string();
+        assert!(text.contains("Added:\n  Carol"));
+        assert!(text.contains(r#"emails: ["alice@x.com"] → ["alice@y.com"]"#));
+        assert!(new.diff_with(&new).is_empty());
+        Ok(())
+    }
+}
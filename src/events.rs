@@ -0,0 +1,93 @@
+use chrono::{Datelike, NaiveDate};
+use std::fmt;
+
+use crate::contact::Contact;
+use crate::store::Store;
+
+/// Which recurring date an [`UpcomingEvent`] comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventKind {
+    Birthday,
+    Anniversary,
+}
+
+impl fmt::Display for EventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EventKind::Birthday => f.write_str("Birthday"),
+            EventKind::Anniversary => f.write_str("Anniversary"),
+        }
+    }
+}
+
+/// A birthday or anniversary falling in the requested month. `date` is the
+/// original stored date (including its year).
+#[derive(Debug, Clone, Copy)]
+pub struct UpcomingEvent<'a> {
+    pub contact: &'a Contact,
+    pub date: NaiveDate,
+    pub kind: EventKind,
+}
+
+impl Store {
+    /// Birthdays and anniversaries in `month` (1-12), ordered by day of month.
+    /// A contact with both dates in that month yields two events.
+    pub fn upcoming_events_in_month(&self, month: u32) -> Vec<UpcomingEvent<'_>> {
+        let mut events = Vec::new();
+        for contact in self.contacts() {
+            let dates = [
+                (contact.birthday, EventKind::Birthday),
+                (contact.anniversary, EventKind::Anniversary),
+            ];
+            for (date, kind) in dates {
+                if let Some(date) = date.filter(|d| d.month() == month) {
+                    events.push(UpcomingEvent {
+                        contact,
+                        date,
+                        kind,
+                    });
+                }
+            }
+        }
+        events.sort_by_key(|e| e.date.day());
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+
+    fn date(s: &str) -> NaiveDate {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn contact_with_both_dates_appears_twice() -> Result<()> {
+        let mut store = Store::default();
+        let mut alice = Contact::new("Alice", "alice@x.com", None)?;
+        alice.birthday = Some(date("1990-06-20"));
+        alice.anniversary = Some(date("2015-06-03"));
+        store.add(alice);
+        let mut bob = Contact::new("Bob", "bob@x.com", None)?;
+        bob.birthday = Some(date("1985-07-01"));
+        store.add(bob);
+
+        let june = store.upcoming_events_in_month(6);
+        let got: Vec<(&str, EventKind, u32)> = june
+            .iter()
+            .map(|e| (e.contact.name.as_str(), e.kind, e.date.day()))
+            .collect();
+        assert_eq!(
+            got,
+            [
+                ("Alice", EventKind::Anniversary, 3),
+                ("Alice", EventKind::Birthday, 20),
+            ]
+        );
+        assert_eq!(store.upcoming_events_in_month(7).len(), 1);
+        assert!(store.upcoming_events_in_month(8).is_empty());
+        Ok(())
+    }
+}
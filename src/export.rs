@@ -0,0 +1,331 @@
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use serde::Serialize;
+use serde_json::ser::PrettyFormatter;
+use serde_json::{Map, Value};
+use std::collections::BTreeMap;
+use std::io::Write;
+
+use crate::contact::Contact;
+use crate::store::Store;
+
+/// Output formats for [`Store::export`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Json,
+    Csv,
+    Tsv,
+    /// One schema.org `Person` JSON-LD object per line.
+    JsonLd,
+}
+
+impl ExportFormat {
+    /// File extension for exports written to a directory.
+    pub fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Tsv => "tsv",
+            ExportFormat::JsonLd => "jsonld",
+        }
+    }
+}
+
+/// What `export --group-by` splits contacts on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupField {
+    Company,
+    /// A contact with several tags is in several groups.
+    Tag,
+}
+
+/// Every exportable field, in default column order. These are the JSON keys
+/// of a serialized [`Contact`]; `--fields` additionally accepts `email`, the
+/// primary address on its own.
+pub const EXPORT_FIELDS: &[&str] = &[
+    "id",
+    "name",
+    "emails",
+    "phone",
+    "tags",
+    "created_at",
+    "updated_at",
+    "birthday",
+    "anniversary",
+    "timezone",
+    "company",
+    "address",
+    "social_handles",
+    "archived",
+    "archived_at",
+];
+
+impl Store {
+    /// Write all contacts to `out` in `format`. `fields` selects and orders
+    /// the exported fields; empty means all of [`EXPORT_FIELDS`].
+    pub fn export(&self, format: ExportFormat, fields: &[String], out: impl Write) -> Result<()> {
+        let contacts: Vec<&Contact> = self.contacts().iter().collect();
+        export_contacts(&contacts, format, fields, out)
+    }
+
+    /// All contacts, archived ones included, keyed by their value of
+    /// `field`. Contacts without one (no company, no tags) are left out.
+    pub fn group_by_field(&self, field: GroupField) -> BTreeMap<String, Vec<&Contact>> {
+        let mut groups: BTreeMap<String, Vec<&Contact>> = BTreeMap::new();
+        for c in self.contacts() {
+            let keys = match field {
+                GroupField::Company => c.company.iter().cloned().collect(),
+                GroupField::Tag => c.tags.clone(),
+            };
+            for key in keys {
+                groups.entry(key).or_default().push(c);
+            }
+        }
+        groups
+    }
+}
+
+pub fn export_contacts(
+    contacts: &[&Contact],
+    format: ExportFormat,
+    fields: &[String],
+    out: impl Write,
+) -> Result<()> {
+    write_export(contacts, format, fields, out, true, Some(2))
+}
+
+/// Like [`export_contacts`] with [`ExportFormat::Json`], indented by
+/// `indent` spaces per level, or on a single line if `indent` is `None`.
+pub fn export_json(
+    contacts: &[&Contact],
+    fields: &[String],
+    indent: Option<usize>,
+    out: impl Write,
+) -> Result<()> {
+    write_export(contacts, ExportFormat::Json, fields, out, true, indent)
+}
+
+/// Like [`export_contacts`], but without the CSV/TSV header row, for
+/// appending to an earlier export. JSON can't be appended to and is rejected.
+pub fn export_rows(
+    contacts: &[&Contact],
+    format: ExportFormat,
+    fields: &[String],
+    out: impl Write,
+) -> Result<()> {
+    if format == ExportFormat::Json {
+        return Err(anyhow!("JSON exports cannot be appended to"));
+    }
+    write_export(contacts, format, fields, out, false, None)
+}
+
+fn write_export(
+    contacts: &[&Contact],
+    format: ExportFormat,
+    fields: &[String],
+    mut out: impl Write,
+    header: bool,
+    json_indent: Option<usize>,
+) -> Result<()> {
+    if format == ExportFormat::JsonLd {
+        if !fields.is_empty() {
+            return Err(anyhow!("--fields cannot be used with json-ld"));
+        }
+        for c in contacts {
+            writeln!(out, "{}", c.to_json_ld())?;
+        }
+        return Ok(());
+    }
+
+    let fields: Vec<&str> = if fields.is_empty() {
+        EXPORT_FIELDS.to_vec()
+    } else {
+        for f in fields {
+            if f != "email" && !EXPORT_FIELDS.contains(&f.as_str()) {
+                return Err(anyhow!(
+                    "unknown field '{}' (valid: {})",
+                    f,
+                    EXPORT_FIELDS.join(", ")
+                ));
+            }
+        }
+        fields.iter().map(String::as_str).collect()
+    };
+
+    let rows = contacts
+        .iter()
+        .map(|c| select_fields(c, &fields))
+        .collect::<Result<Vec<_>>>()?;
+
+    match format {
+        ExportFormat::Json => {
+            let rows: Vec<Value> = rows.into_iter().map(Value::Object).collect();
+            match json_indent {
+                Some(n) => {
+                    let indent = vec![b' '; n];
+                    let formatter = PrettyFormatter::with_indent(&indent);
+                    let mut ser = serde_json::Serializer::with_formatter(&mut out, formatter);
+                    rows.serialize(&mut ser)
+                }
+                None => serde_json::to_writer(&mut out, &rows),
+            }
+            .with_context(|| "writing JSON export")?;
+            writeln!(out)?;
+        }
+        ExportFormat::JsonLd => unreachable!("handled above"),
+        ExportFormat::Csv | ExportFormat::Tsv => {
+            let delimiter = if format == ExportFormat::Tsv {
+                b'\t'
+            } else {
+                b','
+            };
+            let mut w = csv::WriterBuilder::new()
+                .delimiter(delimiter)
+                .from_writer(&mut out);
+            if header {
+                w.write_record(&fields)?;
+            }
+            for row in &rows {
+                w.write_record(fields.iter().map(|f| cell(&row[*f])))?;
+            }
+            w.flush()?;
+        }
+    }
+    Ok(())
+}
+
+/// Serialize to a JSON object and keep only `fields`.
+fn select_fields(c: &Contact, fields: &[&str]) -> Result<Map<String, Value>> {
+    let Value::Object(mut all) = serde_json::to_value(c)? else {
+        return Err(anyhow!("contact did not serialize to an object"));
+    };
+    Ok(fields
+        .iter()
+        .map(|&f| {
+            let value = match f {
+                "email" => Value::from(c.primary_email()),
+                _ => all.remove(f).unwrap_or(Value::Null),
+            };
+            (f.to_string(), value)
+        })
+        .collect())
+}
+
+/// Flatten a JSON value into a single spreadsheet cell.
+fn cell(v: &Value) -> String {
+    match v {
+        Value::Null => String::new(),
+        Value::String(s) => s.clone(),
+        Value::Array(items) => items.iter().map(cell).collect::<Vec<_>>().join(";"),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Result<Store> {
+        let mut store = Store::default();
+        let mut c = Contact::new("Smith, Alice", "alice@x.com", Some("555"))?;
+        c.add_tag("work")?;
+        c.add_tag("vip")?;
+        store.add(c);
+        store.add(Contact::new("Bob", "bob@x.com", None)?);
+        Ok(store)
+    }
+
+    fn export(store: &Store, format: ExportFormat, fields: &[&str]) -> Result<String> {
+        let fields: Vec<String> = fields.iter().map(|f| f.to_string()).collect();
+        let mut out = Vec::new();
+        store.export(format, &fields, &mut out)?;
+        Ok(String::from_utf8(out)?)
+    }
+
+    #[test]
+    fn group_by_tag_repeats_multi_tag_contacts() -> Result<()> {
+        let store = sample()?;
+        let groups = store.group_by_field(GroupField::Tag);
+        assert_eq!(groups.keys().collect::<Vec<_>>(), ["vip", "work"]);
+        assert!(groups.values().all(|g| g.len() == 1));
+        assert!(store.group_by_field(GroupField::Company).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn export_fields_match_contact_keys() -> Result<()> {
+        let v = serde_json::to_value(Contact::new("A", "a@x.com", None)?)?;
+        let mut keys: Vec<&str> = v.as_object().unwrap().keys().map(String::as_str).collect();
+        let mut expected = EXPORT_FIELDS.to_vec();
+        keys.sort();
+        expected.sort();
+        assert_eq!(keys, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn csv_fields_selects_columns_in_order() -> Result<()> {
+        let out = export(&sample()?, ExportFormat::Csv, &["email", "name"])?;
+        let mut lines = out.lines();
+        assert_eq!(lines.next(), Some("email,name"));
+        assert_eq!(lines.next(), Some("alice@x.com,\"Smith, Alice\""));
+        assert_eq!(lines.next(), Some("bob@x.com,Bob"));
+        Ok(())
+    }
+
+    #[test]
+    fn tsv_flattens_lists() -> Result<()> {
+        let out = export(&sample()?, ExportFormat::Tsv, &["name", "tags", "emails"])?;
+        assert_eq!(
+            out.lines().collect::<Vec<_>>(),
+            [
+                "name\ttags\temails",
+                "Smith, Alice\twork;vip\talice@x.com",
+                "Bob\t\tbob@x.com"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn json_fields_keeps_only_requested_keys() -> Result<()> {
+        let out = export(&sample()?, ExportFormat::Json, &["name", "email"])?;
+        let v: Vec<Map<String, Value>> = serde_json::from_str(&out)?;
+        assert_eq!(v.len(), 2);
+        assert_eq!(v[0].len(), 2);
+        assert_eq!(v[1]["name"], "Bob");
+        Ok(())
+    }
+
+    #[test]
+    fn json_ld_writes_one_object_per_line() -> Result<()> {
+        let store = sample()?;
+        let out = export(&store, ExportFormat::JsonLd, &[])?;
+        let lines: Vec<&str> = out.lines().collect();
+        assert_eq!(lines.len(), store.contacts().len());
+        for line in lines {
+            let v: Value = serde_json::from_str(line)?;
+            assert_eq!(v["@type"], "Person");
+        }
+        assert!(export(&store, ExportFormat::JsonLd, &["name"]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn rows_only_skips_header() -> Result<()> {
+        let store = sample()?;
+        let contacts: Vec<&Contact> = store.contacts().iter().collect();
+        let fields = vec!["name".to_string()];
+        let mut out = Vec::new();
+        export_rows(&contacts, ExportFormat::Csv, &fields, &mut out)?;
+        assert_eq!(String::from_utf8(out)?, "\"Smith, Alice\"\nBob\n");
+        assert!(export_rows(&contacts, ExportFormat::Json, &fields, Vec::new()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn unknown_field_is_rejected() -> Result<()> {
+        assert!(export(&sample()?, ExportFormat::Csv, &["nickname"]).is_err());
+        Ok(())
+    }
+}
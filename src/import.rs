@@ -0,0 +1,532 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::NaiveDate;
+use clap::ValueEnum;
+use std::fmt;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+use std::ops::AddAssign;
+use std::path::Path;
+use std::str::FromStr;
+use uuid::Uuid;
+
+use crate::contact::{Address, Contact, ContactUpdate};
+use crate::store::Store;
+
+/// Input formats for `import`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ImportFormat {
+    /// A JSON array of contacts, as in the data file.
+    Json,
+    /// One contact per row; see [`contacts_from_csv`].
+    Csv,
+    /// One JSON contact per line.
+    Ndjson,
+}
+
+impl ImportFormat {
+    /// Work out the format of `path` from its extension (`.json`, `.ndjson`
+    /// or `.jsonl`, `.csv`), or from its first bytes if the extension says
+    /// nothing. CSV exports from Google Contacts and Outlook are recognised
+    /// by their header row and rejected, since their columns need `--map`.
+    pub fn detect(path: &Path) -> Result<ImportFormat> {
+        let ext = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match ext.as_deref() {
+            Some("json") => Ok(ImportFormat::Json),
+            Some("ndjson" | "jsonl") => Ok(ImportFormat::Ndjson),
+            Some("csv") => {
+                check_csv_header(path)?;
+                Ok(ImportFormat::Csv)
+            }
+            Some("vcf" | "vcard") => Err(vcard_unsupported(path)),
+            _ => {
+                let mut magic = [0u8; 4];
+                let n = File::open(path)
+                    .and_then(|mut f| f.read(&mut magic))
+                    .with_context(|| format!("reading import file {}", path.display()))?;
+                match &magic[..n] {
+                    [b'[', ..] => Ok(ImportFormat::Json),
+                    [b'{', ..] => Ok(ImportFormat::Ndjson),
+                    b"BEGI" => Err(vcard_unsupported(path)),
+                    _ => Err(anyhow!(
+                        "cannot tell the format of {}; pass --format json, ndjson or csv",
+                        path.display()
+                    )),
+                }
+            }
+        }
+    }
+}
+
+fn vcard_unsupported(path: &Path) -> anyhow::Error {
+    anyhow!(
+        "{} is a vCard file, which import does not read; export it as CSV instead",
+        path.display()
+    )
+}
+
+/// Fail on CSV exports whose columns don't match our field names. Their
+/// headers are distinctive: Google numbers repeated fields ("E-mail 1 -
+/// Value"), Outlook spells out "E-mail Address".
+fn check_csv_header(path: &Path) -> Result<()> {
+    let file =
+        File::open(path).with_context(|| format!("reading import file {}", path.display()))?;
+    let mut header = String::new();
+    BufReader::new(file)
+        .read_line(&mut header)
+        .with_context(|| format!("reading import file {}", path.display()))?;
+    let source = if header.contains("E-mail 1 - Value") {
+        "Google Contacts"
+    } else if header.contains("E-mail Address") && header.contains("First Name") {
+        "Outlook"
+    } else {
+        return Ok(());
+    };
+    Err(anyhow!(
+        "{} looks like a {} export; pass --format csv with --map COLUMN=FIELD \
+         for the columns to read",
+        path.display(),
+        source
+    ))
+}
+
+/// A contact field a CSV column can fill. Names match the export columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContactField {
+    Name,
+    /// The primary address; `;` separates extra ones.
+    Email,
+    Phone,
+    /// `;`-separated.
+    Tags,
+    Birthday,
+    Anniversary,
+    Timezone,
+    Company,
+    Street,
+    City,
+    State,
+    PostalCode,
+    Country,
+}
+
+impl ContactField {
+    /// Every field, in the column order of `export --template`.
+    pub const ALL: [ContactField; 13] = [
+        ContactField::Name,
+        ContactField::Email,
+        ContactField::Phone,
+        ContactField::Tags,
+        ContactField::Birthday,
+        ContactField::Anniversary,
+        ContactField::Timezone,
+        ContactField::Company,
+        ContactField::Street,
+        ContactField::City,
+        ContactField::State,
+        ContactField::PostalCode,
+        ContactField::Country,
+    ];
+
+    /// The CSV column name.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ContactField::Name => "name",
+            ContactField::Email => "email",
+            ContactField::Phone => "phone",
+            ContactField::Tags => "tags",
+            ContactField::Birthday => "birthday",
+            ContactField::Anniversary => "anniversary",
+            ContactField::Timezone => "timezone",
+            ContactField::Company => "company",
+            ContactField::Street => "street",
+            ContactField::City => "city",
+            ContactField::State => "state",
+            ContactField::PostalCode => "postal_code",
+            ContactField::Country => "country",
+        }
+    }
+}
+
+impl FromStr for ContactField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if s == "emails" {
+            return Ok(ContactField::Email);
+        }
+        ContactField::ALL
+            .into_iter()
+            .find(|f| f.as_str() == s)
+            .ok_or_else(|| anyhow!("unknown contact field '{}'", s))
+    }
+}
+
+/// `--map FROM=TO`: read column `from` into field `to`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnMapping {
+    pub from: String,
+    pub to: ContactField,
+}
+
+impl FromStr for ColumnMapping {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (from, to) = s
+            .split_once('=')
+            .ok_or_else(|| anyhow!("expected COLUMN=FIELD, got '{}'", s))?;
+        Ok(ColumnMapping {
+            from: from.trim().to_string(),
+            to: to.trim().parse()?,
+        })
+    }
+}
+
+/// Read contacts from CSV with a header row. A column is used if `mappings`
+/// names it, or if its header is itself a field name (so exported CSV reads
+/// back in). Each row yields its own result, so one bad row doesn't stop the
+/// rest; an unreadable header fails the whole import. Rows are read only as
+/// the returned iterator is advanced.
+pub fn contacts_from_csv<R: Read>(
+    reader: R,
+    mappings: &[ColumnMapping],
+) -> Result<impl Iterator<Item = Result<Contact>>> {
+    let mut rdr = csv::Reader::from_reader(reader);
+    let columns: Vec<Option<ContactField>> = rdr
+        .headers()
+        .with_context(|| "reading CSV header")?
+        .iter()
+        .map(|h| match mappings.iter().find(|m| m.from == h) {
+            Some(m) => Some(m.to),
+            None => h.parse().ok(),
+        })
+        .collect();
+    Ok(rdr.into_records().map(move |record| {
+        let record = record.with_context(|| "reading CSV row")?;
+        contact_from_row(&columns, &record)
+    }))
+}
+
+fn contact_from_row(
+    columns: &[Option<ContactField>],
+    record: &csv::StringRecord,
+) -> Result<Contact> {
+    let value = |field: ContactField| {
+        columns
+            .iter()
+            .zip(record.iter())
+            .find(|(c, v)| **c == Some(field) && !v.trim().is_empty())
+            .map(|(_, v)| v.trim())
+    };
+    let list = |field: ContactField| -> Vec<&str> {
+        value(field)
+            .map(|v| {
+                v.split(';')
+                    .map(str::trim)
+                    .filter(|s| !s.is_empty())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    let date = |field: ContactField| -> Result<Option<NaiveDate>> {
+        value(field)
+            .map(|v| v.parse().with_context(|| format!("invalid date '{}'", v)))
+            .transpose()
+    };
+
+    let name = value(ContactField::Name).ok_or_else(|| anyhow!("missing name"))?;
+    let emails = list(ContactField::Email);
+    let (email, extra_emails) = emails
+        .split_first()
+        .ok_or_else(|| anyhow!("missing email"))?;
+    let mut c = Contact::new(name, email, value(ContactField::Phone))?;
+    let address = Address {
+        street: value(ContactField::Street).map(String::from),
+        city: value(ContactField::City).map(String::from),
+        state: value(ContactField::State).map(String::from),
+        postal_code: value(ContactField::PostalCode).map(String::from),
+        country: value(ContactField::Country).map(String::from),
+    };
+    c.apply(ContactUpdate {
+        extra_emails: extra_emails.iter().map(|e| e.to_string()).collect(),
+        timezone: value(ContactField::Timezone).map(String::from),
+        company: value(ContactField::Company).map(String::from),
+        address: (!address.is_empty()).then_some(address),
+        ..Default::default()
+    })?;
+    for tag in list(ContactField::Tags) {
+        c.add_tag(tag)?;
+    }
+    c.birthday = date(ContactField::Birthday)?;
+    c.anniversary = date(ContactField::Anniversary)?;
+    Ok(c)
+}
+
+/// What [`Store::import_from_store`] does with a contact whose primary email
+/// (or id) is already in the store.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DuplicatePolicy {
+    /// Leave the stored contact alone and skip the incoming one.
+    #[default]
+    Reject,
+    /// Replace the stored contact's fields, keeping its id.
+    Overwrite,
+    /// Add the incoming contact anyway, with a fresh id if its id is taken.
+    KeepBoth,
+}
+
+/// A row (or, for JSON, an array entry) that could not be imported.
+/// Rows count from 1, not including a CSV header.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ImportError {
+    pub row: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ImportError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "row {}: {}", self.row, self.message)
+    }
+}
+
+/// Counts reported by the import operations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ImportResult {
+    pub imported: usize,
+    pub skipped: usize,
+    pub updated: usize,
+}
+
+impl AddAssign for ImportResult {
+    fn add_assign(&mut self, other: Self) {
+        self.imported += other.imported;
+        self.skipped += other.skipped;
+        self.updated += other.updated;
+    }
+}
+
+impl Store {
+    /// Import `contacts`, treating any whose primary email (or id) is already in the
+    /// store as existing: those are skipped, or replace the stored contact's
+    /// fields when `overwrite` is set. The stored contact keeps its id.
+    pub fn import_deduped(&mut self, contacts: Vec<Contact>, overwrite: bool) -> ImportResult {
+        let mut result = ImportResult::default();
+        for mut incoming in contacts {
+            let existing_id = self
+                .find_by_email(incoming.primary_email())
+                .or_else(|| self.find_by_id(&incoming.id))
+                .map(|c| c.id.clone());
+            match existing_id {
+                Some(id) if overwrite => {
+                    incoming.id = id.clone();
+                    self.replace_contact(&id, incoming);
+                    result.updated += 1;
+                }
+                Some(_) => result.skipped += 1,
+                None => {
+                    self.add(incoming);
+                    result.imported += 1;
+                }
+            }
+        }
+        result
+    }
+
+    /// Merge a copy of every contact in `other` (archived ones included)
+    /// into this store.
+    pub fn import_from_store(&mut self, other: &Store, policy: DuplicatePolicy) -> ImportResult {
+        let contacts = other.contacts().to_vec();
+        match policy {
+            DuplicatePolicy::Reject => self.import_deduped(contacts, false),
+            DuplicatePolicy::Overwrite => self.import_deduped(contacts, true),
+            DuplicatePolicy::KeepBoth => self.import_all(contacts),
+        }
+    }
+
+    /// Import every contact, giving a fresh id to any whose id is taken.
+    pub fn import_all(&mut self, contacts: Vec<Contact>) -> ImportResult {
+        let mut result = ImportResult::default();
+        for mut incoming in contacts {
+            if self.find_by_id(&incoming.id).is_some() {
+                incoming.id = Uuid::new_v4().to_string();
+            }
+            self.add(incoming);
+            result.imported += 1;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn formats_are_detected() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = |name: &str, text: &str| -> Result<std::path::PathBuf> {
+            let path = dir.path().join(name);
+            std::fs::write(&path, text)?;
+            Ok(path)
+        };
+        let detect = |name: &str, text: &str| ImportFormat::detect(&file(name, text)?);
+
+        assert_eq!(detect("a.json", "[]")?, ImportFormat::Json);
+        assert_eq!(detect("a.JSON", "[]")?, ImportFormat::Json);
+        assert_eq!(detect("a.ndjson", "{}")?, ImportFormat::Ndjson);
+        assert_eq!(detect("a.jsonl", "{}")?, ImportFormat::Ndjson);
+        assert_eq!(detect("a.csv", "name,email\n")?, ImportFormat::Csv);
+        assert_eq!(detect("backup", "[{}]")?, ImportFormat::Json);
+        assert_eq!(detect("backup.txt", "{}\n{}")?, ImportFormat::Ndjson);
+
+        let google = "Name,Given Name,E-mail 1 - Type,E-mail 1 - Value\n";
+        assert!(detect("google.csv", google)
+            .unwrap_err()
+            .to_string()
+            .contains("Google Contacts"));
+        let outlook = "First Name,Last Name,E-mail Address\n";
+        assert!(detect("outlook.csv", outlook)
+            .unwrap_err()
+            .to_string()
+            .contains("Outlook"));
+        assert!(detect("a.vcf", "BEGIN:VCARD").is_err());
+        assert!(detect("cards", "BEGIN:VCARD").is_err());
+        let unknown = detect("notes", "hello").unwrap_err().to_string();
+        assert!(unknown.contains("json, ndjson or csv"));
+        Ok(())
+    }
+
+    #[test]
+    fn field_names_round_trip() -> Result<()> {
+        for field in ContactField::ALL {
+            assert_eq!(field.as_str().parse::<ContactField>()?, field);
+        }
+        assert_eq!("emails".parse::<ContactField>()?, ContactField::Email);
+        assert!("nickname".parse::<ContactField>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn csv_columns_can_be_mapped() -> Result<()> {
+        let csv = "full_name,mail,phone,ignored\n\
+                   Alice,alice@x.com;a@work.com,555,x\n\
+                   ,nobody@x.com,,\n\
+                   Bob,,,\n";
+        let mappings: Vec<ColumnMapping> = vec!["full_name=name".parse()?, "mail=email".parse()?];
+        let rows: Vec<_> = contacts_from_csv(csv.as_bytes(), &mappings)?.collect();
+        assert_eq!(rows.len(), 3);
+
+        let alice = rows[0].as_ref().unwrap();
+        assert_eq!(alice.name, "Alice");
+        assert_eq!(alice.emails, ["alice@x.com", "a@work.com"]);
+        assert_eq!(alice.phone.as_deref(), Some("555"));
+        assert!(rows[1]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("missing name"));
+        assert!(rows[2]
+            .as_ref()
+            .unwrap_err()
+            .to_string()
+            .contains("missing email"));
+
+        assert!("full_name".parse::<ColumnMapping>().is_err());
+        assert!("a=nickname".parse::<ColumnMapping>().is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn import_from_store_follows_policy() -> Result<()> {
+        let mut store = existing_store()?;
+        let mut copy = Store::default();
+        copy.replace_contacts(store.contacts().to_vec());
+        let n = store.total_len();
+        let result = store.import_from_store(&copy, DuplicatePolicy::Reject);
+        assert_eq!(
+            result,
+            ImportResult {
+                imported: 0,
+                skipped: n,
+                updated: 0
+            }
+        );
+        assert_eq!(store.total_len(), n);
+
+        let mut other = Store::default();
+        other.add(Contact::new("Zed", "zed@x.com", None)?);
+        other.add(Contact::new("Yan", "yan@x.com", None)?);
+        let result = store.import_from_store(&other, DuplicatePolicy::Reject);
+        assert_eq!(result.imported, 2);
+        assert_eq!(store.total_len(), n + 2);
+
+        let result = store.import_from_store(&other, DuplicatePolicy::KeepBoth);
+        assert_eq!(result.imported, 2);
+        assert_eq!(store.total_len(), n + 4);
+        Ok(())
+    }
+
+    fn existing_store() -> Result<Store> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice", "alice@x.com", None)?);
+        store.add(Contact::new("Bob", "bob@x.com", None)?);
+        Ok(store)
+    }
+
+    fn incoming() -> Result<Vec<Contact>> {
+        Ok(vec![
+            Contact::new("Alice Updated", "ALICE@x.com", Some("555"))?,
+            Contact::new("Carol", "carol@x.com", None)?,
+        ])
+    }
+
+    #[test]
+    fn dedupe_skips_existing_emails() -> Result<()> {
+        let mut store = existing_store()?;
+        let r = store.import_deduped(incoming()?, false);
+        assert_eq!(
+            r,
+            ImportResult {
+                imported: 1,
+                skipped: 1,
+                updated: 0
+            }
+        );
+        assert_eq!(store.contacts().len(), 3);
+        assert_eq!(store.contacts()[0].name, "Alice");
+        Ok(())
+    }
+
+    #[test]
+    fn dedupe_overwrite_updates_in_place() -> Result<()> {
+        let mut store = existing_store()?;
+        let alice_id = store.contacts()[0].id.clone();
+        let r = store.import_deduped(incoming()?, true);
+        assert_eq!(
+            r,
+            ImportResult {
+                imported: 1,
+                skipped: 0,
+                updated: 1
+            }
+        );
+        assert_eq!(store.contacts().len(), 3);
+        let alice = store.find_by_id(&alice_id).unwrap();
+        assert_eq!(alice.name, "Alice Updated");
+        assert_eq!(alice.phone.as_deref(), Some("555"));
+        Ok(())
+    }
+
+    #[test]
+    fn import_all_reassigns_taken_ids() -> Result<()> {
+        let mut store = existing_store()?;
+        let again = store.contacts().to_vec();
+        assert_eq!(store.import_all(again).imported, 2);
+        assert_eq!(store.contacts().len(), 4);
+        assert_ne!(store.contacts()[0].id, store.contacts()[2].id);
+        Ok(())
+    }
+}
@@ -0,0 +1,92 @@
+//! RFC 6902 JSON Patch support, applied to the whole contact array.
+
+use anyhow::{anyhow, Context, Result};
+use serde_json::Value;
+use std::collections::HashSet;
+
+use crate::contact::Contact;
+use crate::store::Store;
+
+impl Store {
+    /// Apply an RFC 6902 patch (a JSON array of operations) to the contact
+    /// array, e.g. `[{"op": "replace", "path": "/0/name", "value": "Al"}]`.
+    ///
+    /// The patch is applied to a copy; the store only changes if every
+    /// operation succeeds and the result still passes contact validation.
+    pub fn apply_patch(&mut self, patch: Value) -> Result<()> {
+        let patch: ::json_patch::Patch =
+            serde_json::from_value(patch).with_context(|| "parsing JSON Patch")?;
+
+        let mut doc = serde_json::to_value(&self.contacts)
+            .with_context(|| "serializing contacts for patching")?;
+        ::json_patch::patch(&mut doc, &patch)
+            .map_err(|e| anyhow!("failed to apply JSON Patch: {}", e))?;
+
+        let contacts: Vec<Contact> = serde_json::from_value(doc)
+            .map_err(|e| anyhow!("patched data is not a valid contact list: {}", e))?;
+        let mut ids = HashSet::new();
+        for c in &contacts {
+            c.validate_with_id()
+                .with_context(|| format!("patched contact {} is invalid", c.id))?;
+            if !ids.insert(c.id.as_str()) {
+                return Err(anyhow!("patch produces duplicate id {}", c.id));
+            }
+        }
+
+        self.replace_contacts(contacts);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::fs;
+    use tempfile::tempdir;
+
+    #[test]
+    fn add_remove_replace_from_patch_file() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice", "alice@x.com", None)?);
+        store.add(Contact::new("Bob", "bob@x.com", None)?);
+        let carol = Contact::new("Carol", "carol@x.com", None)?;
+        let carol_id = carol.id.clone();
+
+        let dir = tempdir()?;
+        let patch_file = dir.path().join("patch.json");
+        let patch = json!([
+            {"op": "replace", "path": "/0/emails/0", "value": "alice@y.com"},
+            {"op": "remove", "path": "/1"},
+            {"op": "add", "path": "/-", "value": carol},
+        ]);
+        fs::write(&patch_file, serde_json::to_vec(&patch)?)?;
+
+        let patch: Value = serde_json::from_slice(&fs::read(&patch_file)?)?;
+        store.apply_patch(patch)?;
+
+        let names: Vec<&str> = store.contacts().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Alice", "Carol"]);
+        assert_eq!(store.contacts()[0].primary_email(), "alice@y.com");
+        assert_eq!(
+            store.find_by_id(&carol_id).map(|c| c.name.as_str()),
+            Some("Carol")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn failed_patch_leaves_store_unchanged() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice", "alice@x.com", None)?);
+
+        let bad_path = json!([{"op": "remove", "path": "/5"}]);
+        assert!(store.apply_patch(bad_path).is_err());
+        let invalid = json!([{"op": "replace", "path": "/0/name", "value": ""}]);
+        assert!(store.apply_patch(invalid).is_err());
+        let bad_id = json!([{"op": "replace", "path": "/0/id", "value": "alice"}]);
+        assert!(store.apply_patch(bad_id).is_err());
+        assert_eq!(store.contacts()[0].name, "Alice");
+        Ok(())
+    }
+}
@@ -0,0 +1,38 @@
+//! Contact model and storage for the secure contacts manager.
+//!
+//! The CLI (`main.rs`) is a thin layer over [`Store`] and [`Contact`];
+//! everything that touches the data file lives here.
+#![forbid(unsafe_code)]
+
+mod audit;
+mod contact;
+mod dedup;
+mod diff;
+mod events;
+mod export;
+mod import;
+mod json_patch;
+mod lint;
+mod profile;
+mod store;
+mod watch;
+
+pub use audit::{AuditEntry, AuditOperation};
+pub use contact::{Address, Contact, ContactBuilder, ContactUpdate, IdFormat, CARD_WIDTH};
+pub use dedup::{DedupStrategy, DuplicatePair, DuplicateReason};
+pub use diff::{ContactChange, FieldChange, StoreDiff};
+pub use events::{EventKind, UpcomingEvent};
+pub use export::{
+    export_contacts, export_json, export_rows, ExportFormat, GroupField, EXPORT_FIELDS,
+};
+pub use import::{
+    contacts_from_csv, ColumnMapping, ContactField, DuplicatePolicy, ImportError, ImportFormat,
+    ImportResult,
+};
+pub use lint::{LintIssue, LintWarning};
+pub use profile::{ColorScheme, Profiles, BUILD_DATA_DIR};
+pub use store::{
+    Counts, Format, SortField, Store, StoreOptions, StoreWarning, DEFAULT_MAX_FILE_SIZE,
+    DEFAULT_TOMBSTONE_TTL_DAYS,
+};
+pub use watch::FileWatcher;
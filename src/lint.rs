@@ -0,0 +1,186 @@
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::contact::Contact;
+use crate::store::Store;
+
+/// A data quality problem found by [`Store::lint`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LintIssue {
+    /// The address is accepted by the loose input check but is not a
+    /// well-formed RFC 5322 `local@domain` address.
+    InvalidEmail(String),
+    /// The name is made up of digits only.
+    NumericName,
+    /// Another contact has the same phone number, comparing digits only.
+    DuplicatePhone { other_id: String },
+    /// `created_at` is later than `updated_at`.
+    CreatedAfterUpdated,
+    /// A tag outside the allowed list.
+    UnknownTag(String),
+}
+
+impl fmt::Display for LintIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LintIssue::InvalidEmail(email) => write!(f, "invalid email address '{}'", email),
+            LintIssue::NumericName => f.write_str("name is only digits"),
+            LintIssue::DuplicatePhone { other_id } => {
+                write!(f, "same phone number as {}", other_id)
+            }
+            LintIssue::CreatedAfterUpdated => f.write_str("created_at is after updated_at"),
+            LintIssue::UnknownTag(tag) => write!(f, "tag '{}' is not in the allowed list", tag),
+        }
+    }
+}
+
+/// One [`LintIssue`] and the contact it was found on.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LintWarning {
+    pub contact_id: String,
+    pub issue: LintIssue,
+}
+
+impl fmt::Display for LintWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.contact_id, self.issue)
+    }
+}
+
+impl Store {
+    /// Check every contact, archived ones included, for likely data entry
+    /// mistakes. Tags are only checked when `allowed_tags` is non-empty.
+    /// Nothing is modified.
+    pub fn lint(&self, allowed_tags: &[&str]) -> Vec<LintWarning> {
+        let mut warnings = Vec::new();
+        let mut phones: HashMap<String, &str> = HashMap::new();
+        for c in self.contacts() {
+            let mut warn = |issue| {
+                warnings.push(LintWarning {
+                    contact_id: c.id.clone(),
+                    issue,
+                })
+            };
+            for email in &c.emails {
+                if !is_strict_email(email) {
+                    warn(LintIssue::InvalidEmail(email.clone()));
+                }
+            }
+            if c.name.chars().all(|ch| ch.is_ascii_digit()) {
+                warn(LintIssue::NumericName);
+            }
+            if let Some(digits) = phone_digits(c) {
+                match phones.get(digits.as_str()) {
+                    Some(other) => warn(LintIssue::DuplicatePhone {
+                        other_id: other.to_string(),
+                    }),
+                    None => {
+                        phones.insert(digits, &c.id);
+                    }
+                }
+            }
+            if let (Some(created), Some(updated)) = (c.created_at, c.updated_at) {
+                if created > updated {
+                    warn(LintIssue::CreatedAfterUpdated);
+                }
+            }
+            if !allowed_tags.is_empty() {
+                for tag in &c.tags {
+                    if !allowed_tags.contains(&tag.as_str()) {
+                        warn(LintIssue::UnknownTag(tag.clone()));
+                    }
+                }
+            }
+        }
+        warnings
+    }
+}
+
+fn phone_digits(c: &Contact) -> Option<String> {
+    let digits: String = c
+        .phone
+        .as_deref()?
+        .chars()
+        .filter(char::is_ascii_digit)
+        .collect();
+    (!digits.is_empty()).then_some(digits)
+}
+
+/// `local@domain` with an RFC 5322 dot-atom on both sides and a domain of
+/// at least two DNS labels. Quoted local parts and address literals are
+/// rejected; they are legal but almost always a typo in a contact list.
+fn is_strict_email(email: &str) -> bool {
+    const ATEXT: &str = "!#$%&'*+-/=?^_`{|}~";
+    let Some((local, domain)) = email.rsplit_once('@') else {
+        return false;
+    };
+    let dot_atom = |s: &str, ok: &dyn Fn(char) -> bool| {
+        !s.is_empty()
+            && s.split('.')
+                .all(|part| !part.is_empty() && part.chars().all(ok))
+    };
+    let local_ok = local.len() <= 64
+        && dot_atom(local, &|ch| {
+            ch.is_ascii_alphanumeric() || ATEXT.contains(ch)
+        });
+    let domain_ok = domain.len() <= 255
+        && domain.contains('.')
+        && dot_atom(domain, &|ch| ch.is_ascii_alphanumeric() || ch == '-')
+        && domain
+            .split('.')
+            .all(|label| label.len() <= 63 && !label.starts_with('-') && !label.ends_with('-'));
+    local_ok && domain_ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Result;
+    use chrono::{Duration, Utc};
+
+    #[test]
+    fn strict_email() {
+        assert!(is_strict_email("alice@example.com"));
+        assert!(is_strict_email("a.b+tag@mail.example.co.uk"));
+        assert!(!is_strict_email("alice"));
+        assert!(!is_strict_email("alice@localhost"));
+        assert!(!is_strict_email("alice..b@example.com"));
+        assert!(!is_strict_email(".alice@example.com"));
+        assert!(!is_strict_email("al ice@example.com"));
+        assert!(!is_strict_email("alice@-example.com"));
+        assert!(!is_strict_email("a@b@example.com"));
+    }
+
+    #[test]
+    fn lint_reports_each_problem() -> Result<()> {
+        let mut store = Store::default();
+        let good = Contact::new("Alice", "alice@example.com", Some("+1 555 0100"))?;
+        let mut bad = Contact::new("12345", "not an email", Some("1-555-0100"))?;
+        let now = Utc::now();
+        bad.created_at = Some(now);
+        bad.updated_at = Some(now - Duration::days(1));
+        bad.add_tag("wrok")?;
+        let (good_id, bad_id) = (good.id.clone(), bad.id.clone());
+        store.add(good);
+        store.add(bad);
+
+        let issues: Vec<LintIssue> = store
+            .lint(&["work"])
+            .into_iter()
+            .inspect(|w| assert_eq!(w.contact_id, bad_id))
+            .map(|w| w.issue)
+            .collect();
+        assert_eq!(
+            issues,
+            [
+                LintIssue::InvalidEmail("not an email".into()),
+                LintIssue::NumericName,
+                LintIssue::DuplicatePhone { other_id: good_id },
+                LintIssue::CreatedAfterUpdated,
+                LintIssue::UnknownTag("wrok".into()),
+            ]
+        );
+        assert_eq!(store.lint(&[]).len(), 4);
+        Ok(())
+    }
+}
@@ -1,12 +1,21 @@
 use anyhow::{anyhow, Context, Result};
-use clap::{Parser, Subcommand};
-use fs2::FileExt;
-use serde::{Deserialize, Serialize};
-use std::fs::{self, OpenOptions};
-use std::io::{Read, Write};
+use chrono::{DateTime, Datelike, NaiveDate, Utc};
+use clap::error::ErrorKind;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use indicatif::{ProgressBar, ProgressStyle};
+use owo_colors::OwoColorize;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use secure_contacts::{
+    contacts_from_csv, export_contacts, export_json, export_rows, Address, AuditOperation,
+    ColorScheme, ColumnMapping, Contact, ContactField, ContactUpdate, Counts, ExportFormat,
+    FileWatcher, Format, GroupField, IdFormat, ImportError, ImportFormat, Profiles, SortField,
+    Store, StoreOptions, BUILD_DATA_DIR, DEFAULT_MAX_FILE_SIZE, DEFAULT_TOMBSTONE_TTL_DAYS,
+};
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, IsTerminal, Write};
 use std::path::{Path, PathBuf};
-use tempfile::NamedTempFile;
-use uuid::Uuid;
+use std::time::{Duration, SystemTime};
 
 /// Simple, secure contacts manager (CLI)
 ///
@@ -21,12 +30,65 @@ use uuid::Uuid;
 #[derive(Parser)]
 #[command(author, version, about)]
 struct Cli {
-    /// Path to the data file (JSON)
-    #[arg(short, long, value_name = "FILE", default_value = "contacts.json")]
-    file: PathBuf,
+    /// Path to the data file; `-` reads JSON from stdin and writes changes to stdout
+    /// [default: $CONTACTS_FILE, else the default profile, else contacts.json]
+    #[arg(short, long, value_name = "FILE")]
+    file: Option<PathBuf>,
+
+    /// Use the named profile's data file instead of --file
+    #[arg(long, value_name = "NAME", conflicts_with = "file")]
+    profile: Option<String>,
+
+    /// Data file format [default: toml for *.toml files, otherwise json]
+    #[arg(long, value_enum)]
+    format: Option<Format>,
+
+    /// Open the data file even if it doesn't match its .sha256 checksum
+    #[arg(long)]
+    skip_verify: bool,
+
+    /// Refuse to open data files larger than this many bytes
+    #[arg(long, value_name = "BYTES", default_value_t = DEFAULT_MAX_FILE_SIZE)]
+    max_file_size: u64,
+
+    /// Delete archived contacts after this many days (0 keeps them forever)
+    #[arg(long, value_name = "DAYS", default_value_t = DEFAULT_TOMBSTONE_TTL_DAYS)]
+    tombstone_ttl_days: u32,
+
+    /// Don't lock the data file (for NFS/FUSE mounts without lock support;
+    /// also `no_lock = true` in the config file)
+    #[arg(long)]
+    no_lock: bool,
+
+    /// How to make ids for new contacts [default: `id_format` from the
+    /// config file, else uuid]
+    #[arg(long, value_enum)]
+    id_format: Option<IdFormat>,
+
+    /// Print extra status messages
+    #[arg(short, long, global = true)]
+    verbose: bool,
+
+    /// Always use colors, even when stdout is not a terminal
+    #[arg(long, global = true, conflicts_with = "no_color")]
+    color: bool,
+
+    /// Never use colors (same as setting NO_COLOR)
+    #[arg(long, global = true)]
+    no_color: bool,
+
+    /// Color palette; `light` suits light terminal backgrounds
+    /// [default: `color_scheme` in the config file, else dark]
+    #[arg(long, global = true, value_enum, value_name = "SCHEME")]
+    color_scheme: Option<ColorScheme>,
+
+    /// Shorthand for `find PATTERN`; may also be given as the query of an
+    /// explicit `find`, e.g. `find --all-tags work -g alice`
+    #[arg(short = 'g', long, global = true, value_name = "PATTERN")]
+    grep: Option<String>,
 
     #[command(subcommand)]
-    command: Commands,
+    command: Option<Commands>,
 }
 
 #[derive(Subcommand)]
@@ -37,297 +99,2308 @@ enum Commands {
         email: String,
         #[arg(short, long)]
         phone: Option<String>,
+        /// Additional email address (repeatable)
+        #[arg(long = "extra-email")]
+        extra_emails: Vec<String>,
+        /// Tag to attach (repeatable)
+        #[arg(short, long = "tag")]
+        tags: Vec<String>,
+        /// Birthday (YYYY-MM-DD)
+        #[arg(long)]
+        birthday: Option<NaiveDate>,
+        /// Wedding or other anniversary (YYYY-MM-DD)
+        #[arg(long)]
+        anniversary: Option<NaiveDate>,
+        /// IANA timezone name, e.g. America/New_York
+        #[arg(long)]
+        timezone: Option<String>,
+        #[arg(long)]
+        company: Option<String>,
+        #[command(flatten)]
+        address: AddressArgs,
+        /// Social handle as PLATFORM=HANDLE (repeatable)
+        #[arg(long, value_name = "PLATFORM=HANDLE", value_parser = parse_social)]
+        social: Vec<(String, String)>,
+        /// Use this UUID v4 as the id instead of generating one
+        #[arg(long, value_name = "UUID")]
+        with_id: Option<String>,
     },
+    /// Update fields of an existing contact
+    Update {
+        id: String,
+        #[arg(long)]
+        name: Option<String>,
+        #[arg(long)]
+        email: Option<String>,
+        #[arg(short, long)]
+        phone: Option<String>,
+        /// IANA timezone name, e.g. America/New_York
+        #[arg(long)]
+        timezone: Option<String>,
+        #[arg(long)]
+        company: Option<String>,
+        /// Social handle as PLATFORM=HANDLE (repeatable)
+        #[arg(long, value_name = "PLATFORM=HANDLE", value_parser = parse_social)]
+        social: Vec<(String, String)>,
+    },
+    /// Change a contact's name (shorthand for `update --name`)
+    Rename { id: String, new_name: String },
     /// Remove a contact by id
-    Remove { id: String },
+    Remove {
+        id: String,
+        /// Ask before deleting (needs a terminal)
+        #[arg(long)]
+        confirm: bool,
+        /// Never ask, even with --confirm
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Restore a contact removed by the last save (from the .undo file).
+    /// Needs the full id: the contact is no longer in the store to match a
+    /// prefix against.
+    Unremove { id: String },
+    /// Hide a contact from list/find without deleting it
+    Archive { id: String },
+    /// Restore an archived contact
+    Unarchive { id: String },
+    /// Show every field of one contact
+    Show {
+        id: String,
+        /// Show the age next to the birthday
+        #[arg(long)]
+        with_age: bool,
+    },
+    /// Show one contact as a business card
+    Card { id: String },
+    /// Show the contact whose primary email is exactly EMAIL (ignoring case)
+    Resolve {
+        #[arg(long)]
+        email: String,
+        /// Print the contact as one line of JSON
+        #[arg(long)]
+        json: bool,
+    },
     /// List all contacts
+    List {
+        /// Extra columns to show (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<ListField>,
+        /// Also show archived contacts
+        #[arg(long)]
+        include_archived: bool,
+        /// Only contacts created at or after this time (RFC 3339)
+        #[arg(long)]
+        since: Option<DateTime<Utc>>,
+        /// Only contacts created before this time (RFC 3339)
+        #[arg(long)]
+        before: Option<DateTime<Utc>>,
+        /// Text between fields; \t and \n are expanded
+        #[arg(long, default_value = " | ", value_parser = unescape_separator)]
+        output_separator: String,
+        /// Append each contact's age in years
+        #[arg(long)]
+        with_age: bool,
+        /// Show one page of active contacts, ordered by id, starting after
+        /// this id (use the id printed as `Next:` by the previous page)
+        #[arg(long, conflicts_with_all = ["include_archived", "since", "before"])]
+        after_id: Option<String>,
+        /// Page size for --after-id; also starts paging from the first contact
+        #[arg(long, conflicts_with_all = ["include_archived", "since", "before"])]
+        limit: Option<usize>,
+        /// Order of the listed contacts
+        #[arg(long, value_enum, default_value_t = SortField::Natural, conflicts_with_all = ["after_id", "limit"])]
+        sort_by: SortField,
+        /// Reverse the order given by --sort-by
+        #[arg(long, conflicts_with_all = ["after_id", "limit"])]
+        reverse: bool,
+    },
+    /// Show the first N contacts, in list order
+    Head(EndArgs),
+    /// Show the last N contacts, in list order
+    Tail(EndArgs),
+    /// Show N random active contacts
+    Random {
+        #[arg(short, long, default_value_t = 1)]
+        n: usize,
+        /// Seed the random choice so the same contacts come back
+        #[arg(long)]
+        seed: Option<u64>,
+    },
+    /// Find contacts by substring (name or email, optionally phone)
+    Find(FindArgs),
+    /// Same as `find`, with the query as a named flag
+    Search {
+        #[arg(long)]
+        query: String,
+        /// Also match archived contacts
+        #[arg(long)]
+        include_archived: bool,
+        /// Also match phone numbers
+        #[arg(long)]
+        phone_search: bool,
+    },
+    /// Suggest up to 5 existing contacts at the same email domain
+    Suggest {
+        /// An email address, or just its domain
+        #[arg(long = "for", value_name = "EMAIL")]
+        query: String,
+    },
+    /// List active contacts that have no phone number
+    MissingPhone,
+    /// List active contacts that have no email address
+    MissingEmail,
+    /// Show birthdays and anniversaries in a month (default: current month)
+    Upcoming {
+        #[arg(value_parser = clap::value_parser!(u32).range(1..=12))]
+        month: Option<u32>,
+    },
+    /// Export contacts as JSON, CSV, TSV or JSON-LD
+    Export {
+        #[arg(long, value_enum, default_value_t = ExportFormat::Json)]
+        format: ExportFormat,
+        /// Write to this file instead of stdout
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Only these fields, in this order (comma-separated)
+        #[arg(long, value_delimiter = ',')]
+        fields: Vec<String>,
+        /// Add to the end of the output file (CSV, TSV and JSON-LD only);
+        /// the header is only written if the file is new
+        #[arg(long, requires = "output")]
+        append: bool,
+        /// Only these contacts, in this order (comma-separated ids)
+        #[arg(long, value_delimiter = ',')]
+        ids: Vec<String>,
+        /// Only contacts modified after this time (RFC 3339)
+        #[arg(long, conflicts_with = "ids")]
+        since: Option<DateTime<Utc>>,
+        /// Write just the CSV header row, naming every column `import`
+        /// reads, as a blank sheet for data entry
+        #[arg(long, conflicts_with_all = ["fields", "append", "ids", "since"])]
+        template: bool,
+        /// Write one file per company or tag into --output-dir
+        #[arg(long, value_enum, requires = "output_dir", conflicts_with_all = ["output", "ids", "since", "template"])]
+        group_by: Option<GroupField>,
+        /// Directory for --group-by files, named after each group
+        #[arg(long, requires = "group_by")]
+        output_dir: Option<PathBuf>,
+        /// Split the export into numbered files: --output is a directory
+        /// (giving DIR/contacts_001.csv, ...) or a base name (BASE_001.csv, ...)
+        #[arg(long, requires = "output", conflicts_with_all = ["append", "template"])]
+        paginate: bool,
+        /// Contacts per file with --paginate
+        #[arg(long, default_value_t = 1000, requires = "paginate", value_parser = clap::value_parser!(u64).range(1..))]
+        page_size: u64,
+        /// Indent JSON output (the default; see --indent)
+        #[arg(long, conflicts_with = "compact")]
+        pretty: bool,
+        /// Write JSON output on a single line
+        #[arg(long)]
+        compact: bool,
+        /// Spaces per indent level in pretty JSON output
+        #[arg(
+            long,
+            value_name = "N",
+            default_value_t = 2,
+            conflicts_with = "compact"
+        )]
+        indent: usize,
+    },
+    /// Import contacts from a JSON file (same format as the data file) or CSV
+    Import {
+        file: PathBuf,
+        /// Skip contacts whose email already exists
+        #[arg(long)]
+        dedupe: bool,
+        /// With --dedupe, update existing contacts instead of skipping them
+        #[arg(long, requires = "dedupe")]
+        overwrite: bool,
+        /// Don't show a progress bar, only the final summary
+        #[arg(short, long)]
+        quiet: bool,
+        /// Input format [default: detected from the extension or contents]
+        #[arg(long, value_enum)]
+        format: Option<ImportFormat>,
+        /// Read CSV column COLUMN into FIELD, e.g. full_name=name (repeatable)
+        #[arg(long = "map", value_name = "COLUMN=FIELD")]
+        mappings: Vec<ColumnMapping>,
+        /// Import the valid rows and list the failed ones, instead of
+        /// importing nothing if any row is invalid
+        #[arg(long)]
+        ignore_errors: bool,
+    },
+    /// Keep importing files dropped into a directory (Ctrl-C to exit);
+    /// imported files are moved to DIR/processed
+    ImportWatch {
+        dir: PathBuf,
+        /// Format of the files to pick up (*.json, *.csv or *.ndjson)
+        #[arg(long, value_enum, default_value_t = ImportFormat::Json)]
+        format: ImportFormat,
+        /// Poll interval in milliseconds
+        #[arg(long, default_value_t = 1000)]
+        interval_ms: u64,
+    },
+    /// Show what changed relative to another contacts file
+    Diff {
+        #[arg(long, value_name = "FILE")]
+        against: PathBuf,
+    },
+    /// Apply an RFC 6902 JSON Patch file to the contact list
+    Patch { patch_file: PathBuf },
+    /// Run commands read from stdin, one per line, then save once
+    Batch,
+    /// Run several commands in order, then save once; stops at the first
+    /// failure without saving. `<all>` stands for each contact found by the
+    /// previous `find`
+    Pipeline {
+        /// One command, e.g. --cmd "find bob" --cmd "archive <all>" (repeatable)
+        #[arg(long = "cmd", value_name = "COMMAND", required = true)]
+        commands: Vec<String>,
+    },
+    /// Re-print the list whenever the data file changes (Ctrl-C to exit)
+    Watch {
+        /// Poll interval in milliseconds
+        #[arg(long, default_value_t = 500)]
+        interval: u64,
+    },
+    /// Create a new, empty data file
+    Init {
+        /// Overwrite the file if it already exists
+        #[arg(long)]
+        force: bool,
+        /// Add one example contact showing the file format
+        #[arg(long)]
+        template: bool,
+    },
+    /// Add a tag to each of the given contacts
+    Tag {
+        tag: String,
+        #[arg(required = true)]
+        ids: Vec<String>,
+    },
+    /// Rename a tag on every contact that has it
+    RenameTag { from: String, to: String },
+    /// Remove a tag from every contact
+    DeleteTag { tag: String },
+    /// Rewrite the data file as compact (minified) JSON
+    Compact {
+        /// Also drop archived contacts and report the size saved
+        #[arg(long)]
+        defrag: bool,
+    },
+    /// Rebuild the id and email lookups from the data file
+    Reindex,
+    /// Permanently delete all archived contacts
+    Purge {
+        /// Don't ask for confirmation (for scripts)
+        #[arg(long)]
+        confirm: bool,
+    },
+    /// Save a copy with names, emails, phones and addresses replaced, for
+    /// sharing in bug reports
+    Anonymize { output: PathBuf },
+    /// Show the 20 most common words in a field (name, company, city, ...)
+    Analyze { field: ContactField },
+    /// Re-check every contact against the current validation rules
+    Validate,
+    /// Report likely data quality problems; exits 1 if there are any
+    Lint {
+        /// Warn about tags outside this list (comma-separated)
+        #[arg(long, value_delimiter = ',', value_name = "TAGS")]
+        allowed_tags: Vec<String>,
+    },
+    /// Show the audit log of saved changes, oldest first
+    AuditLog {
+        /// Only the last N entries
+        #[arg(long)]
+        tail: Option<usize>,
+    },
+    /// Print the version, git commit and build date
+    Version,
+    /// Manage named data files (see --profile)
+    Profile {
+        #[command(subcommand)]
+        action: ProfileAction,
+    },
+}
+
+impl Commands {
+    /// Commands that never modify the store; they need an existing file.
+    fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            Commands::Show { .. }
+                | Commands::Card { .. }
+                | Commands::Resolve { .. }
+                | Commands::List { .. }
+                | Commands::Head(_)
+                | Commands::Tail(_)
+                | Commands::Random { .. }
+                | Commands::Find(_)
+                | Commands::Search { .. }
+                | Commands::Suggest { .. }
+                | Commands::MissingPhone
+                | Commands::MissingEmail
+                | Commands::Upcoming { .. }
+                | Commands::Export { .. }
+                | Commands::Diff { .. }
+                | Commands::Reindex
+                | Commands::Validate
+                | Commands::Lint { .. }
+                | Commands::AuditLog { .. }
+                | Commands::Analyze { .. }
+                | Commands::Anonymize { .. }
+        )
+    }
+}
+
+#[derive(Subcommand)]
+enum ProfileAction {
+    /// Create an empty profile
+    Create { name: String },
+    /// Delete a profile and its data file
+    Delete { name: String },
+    /// List profiles; the default is marked with `*`
     List,
-    /// Find contacts by substring (name or email)
-    Find { query: String },
+    /// Use this profile when neither --profile nor --file is given
+    Default { name: String },
+}
+
+/// Options shared by `head` and `tail`, matching those of `list`.
+#[derive(Args)]
+struct EndArgs {
+    #[arg(short, long, default_value_t = 10)]
+    n: usize,
+    /// Also count archived contacts
+    #[arg(long)]
+    include_archived: bool,
+    /// Order of the contacts before taking N
+    #[arg(long, value_enum, default_value_t = SortField::Natural)]
+    sort_by: SortField,
+    /// Reverse the order given by --sort-by
+    #[arg(long)]
+    reverse: bool,
+    /// Extra columns to show (comma-separated)
+    #[arg(long, value_delimiter = ',')]
+    fields: Vec<ListField>,
+    /// Text between fields; \t and \n are expanded
+    #[arg(long, default_value = " | ", value_parser = unescape_separator)]
+    output_separator: String,
+}
+
+impl EndArgs {
+    /// Print the first (or, with `from_end`, the last) N contacts as `list`
+    /// would order and show them.
+    fn print(&self, store: &Store, out: &mut Printer, from_end: bool) -> Result<()> {
+        let shown: Vec<&Contact> = store
+            .sorted_list(self.sort_by, self.reverse)
+            .into_iter()
+            .filter(|c| self.include_archived || !c.archived)
+            .collect();
+        let start = if from_end {
+            shown.len().saturating_sub(self.n)
+        } else {
+            0
+        };
+        for c in shown.iter().skip(start).take(self.n) {
+            writeln!(
+                out,
+                "{}",
+                list_line(out, c, &self.fields, &self.output_separator)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Args)]
+struct FindArgs {
+    /// Text to look for; may be left out when filtering by tags or company
+    #[arg(required_unless_present_any = ["all_tags", "any_tags", "company_exact", "grep"])]
+    query: Option<String>,
+    /// Also match archived contacts
+    #[arg(long)]
+    include_archived: bool,
+    /// Only contacts created at or after this time (RFC 3339)
+    #[arg(long)]
+    since: Option<DateTime<Utc>>,
+    /// Only contacts created before this time (RFC 3339)
+    #[arg(long)]
+    before: Option<DateTime<Utc>>,
+    /// Also match phone numbers
+    #[arg(long)]
+    phone_search: bool,
+    /// Show the active contacts that do NOT match instead
+    #[arg(long, conflicts_with_all = ["include_archived", "phone_search"])]
+    not: bool,
+    /// Only contacts with every one of these tags (comma-separated)
+    #[arg(long, value_delimiter = ',', value_name = "TAGS")]
+    all_tags: Vec<String>,
+    /// Only contacts with at least one of these tags (comma-separated)
+    #[arg(long, value_delimiter = ',', value_name = "TAGS")]
+    any_tags: Vec<String>,
+    /// Contacts whose company is exactly NAME (ignoring case)
+    #[arg(long, value_name = "NAME", conflicts_with_all = ["query", "not", "phone_search"])]
+    company_exact: Option<String>,
+    /// Leave these fields out of the output; they are still searched
+    #[arg(long, value_enum, value_delimiter = ',', value_name = "FIELDS")]
+    exclude_fields: Vec<FindField>,
+    /// Print only the first match and stop; fail if nothing matches
+    #[arg(
+        long,
+        requires = "query",
+        conflicts_with_all = [
+            "include_archived", "since", "before", "phone_search", "not",
+            "all_tags", "any_tags", "company_exact",
+        ],
+    )]
+    first: bool,
 }
 
-#[derive(Debug, Serialize, Deserialize, Clone)]
-struct Contact {
-    id: String,
-    name: String,
-    email: String,
-    phone: Option<String>,
+impl FindArgs {
+    /// A plain `find QUERY` with no other flags.
+    fn for_query(query: String) -> Self {
+        FindArgs {
+            query: Some(query),
+            include_archived: false,
+            since: None,
+            before: None,
+            phone_search: false,
+            not: false,
+            all_tags: Vec::new(),
+            any_tags: Vec::new(),
+            company_exact: None,
+            exclude_fields: Vec::new(),
+            first: false,
+        }
+    }
+
+    /// Contacts matching the query (inverted by `--not`), then narrowed by
+    /// the date and tag filters.
+    fn matches<'a>(&self, store: &'a Store) -> Vec<&'a Contact> {
+        // An empty query matches every contact.
+        let query = self.query.as_deref().unwrap_or_default();
+        let found = if let Some(company) = &self.company_exact {
+            store
+                .find_by_company_exact(company)
+                .into_iter()
+                .filter(|c| self.include_archived || !c.archived)
+                .collect()
+        } else if self.not {
+            store.find_not(query)
+        } else {
+            store.find(query, self.include_archived, self.phone_search)
+        };
+        let all_tags: Vec<&str> = self.all_tags.iter().map(String::as_str).collect();
+        let any_tags: Vec<&str> = self.any_tags.iter().map(String::as_str).collect();
+        found
+            .into_iter()
+            .filter(|c| c.created_between(self.since, self.before))
+            .filter(|c| c.has_all_tags(&all_tags))
+            .filter(|c| any_tags.is_empty() || c.has_any_tag(&any_tags))
+            .collect()
+    }
+}
+
+#[derive(Args)]
+struct AddressArgs {
+    #[arg(long)]
+    street: Option<String>,
+    #[arg(long)]
+    city: Option<String>,
+    #[arg(long)]
+    state: Option<String>,
+    #[arg(long)]
+    postal: Option<String>,
+    #[arg(long)]
+    country: Option<String>,
+}
+
+impl AddressArgs {
+    fn into_address(self) -> Option<Address> {
+        let address = Address {
+            street: self.street,
+            city: self.city,
+            state: self.state,
+            postal_code: self.postal,
+            country: self.country,
+        };
+        (!address.is_empty()).then_some(address)
+    }
+}
+
+fn parse_social(s: &str) -> Result<(String, String), String> {
+    s.split_once('=')
+        .map(|(p, h)| (p.to_string(), h.to_string()))
+        .ok_or_else(|| format!("expected PLATFORM=HANDLE, got '{}'", s))
+}
+
+/// `import` advances its progress bar once per this many rows.
+const IMPORT_CHUNK: usize = 100;
+
+/// Output sink for command results, adding ANSI styles when `color` is set.
+struct Printer<'a> {
+    out: &'a mut dyn Write,
+    colors: ColorScheme,
 }
 
-impl Contact {
-    fn new(name: &str, email: &str, phone: Option<&str>) -> Result<Self> {
-        // Input validation & length limits
-        if name.trim().is_empty() || email.trim().is_empty() {
-            return Err(anyhow!("name and email must be non-empty"));
+impl<'a> Printer<'a> {
+    fn new(out: &'a mut dyn Write, colors: ColorScheme) -> Self {
+        Printer { out, colors }
+    }
+
+    fn style(&self, s: &str, f: impl Fn(&str) -> String) -> String {
+        if self.colors == ColorScheme::None {
+            s.to_string()
+        } else {
+            f(s)
         }
-        if name.len() > 200 {
-            return Err(anyhow!("name too long (max 200 chars)"));
+    }
+
+    fn id(&self, s: &str) -> String {
+        self.style(s, |s| s.dimmed().to_string())
+    }
+
+    fn name(&self, s: &str) -> String {
+        self.style(s, |s| s.bold().to_string())
+    }
+
+    fn label(&self, s: &str) -> String {
+        match self.colors {
+            ColorScheme::Light => self.style(s, |s| s.blue().to_string()),
+            _ => self.style(s, |s| s.cyan().to_string()),
         }
-        if email.len() > 320 {
-            return Err(anyhow!("email too long (max 320 chars)"));
+    }
+}
+
+impl Write for Printer<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.out.write(buf)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
+
+/// Decide whether to color stdout. An explicit `--color`/`--no-color`
+/// (`force`) wins; otherwise NO_COLOR (any value), TERM=dumb, or a non-TTY
+/// stdout each turn colors off.
+fn should_use_color(force: Option<bool>) -> bool {
+    color_decision(
+        force,
+        std::env::var_os("NO_COLOR").is_some(),
+        std::env::var_os("TERM").is_some_and(|t| t == "dumb"),
+        std::io::stdout().is_terminal(),
+    )
+}
+
+fn color_decision(force: Option<bool>, no_color_env: bool, dumb_term: bool, tty: bool) -> bool {
+    match force {
+        Some(forced) => forced,
+        None => !no_color_env && !dumb_term && tty,
+    }
+}
+
+/// Optional extra columns for `list`.
+#[derive(Clone, Copy, ValueEnum)]
+enum ListField {
+    City,
+    Country,
+}
+
+/// Fields shown by `find`, for `--exclude-fields`.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+enum FindField {
+    Name,
+    Phone,
+}
+
+/// One `find` result: name and phone, minus the excluded fields.
+fn find_line(c: &Contact, exclude: &[FindField]) -> String {
+    let mut parts = Vec::new();
+    if !exclude.contains(&FindField::Name) {
+        parts.push(c.name.as_str());
+    }
+    if !exclude.contains(&FindField::Phone) {
+        parts.push(c.phone.as_deref().unwrap_or("No phone"));
+    }
+    parts.join(" - ")
+}
+
+fn list_line(p: &Printer, c: &Contact, fields: &[ListField], separator: &str) -> String {
+    let mut parts = vec![p.id(&c.id), p.name(&c.name), c.primary_email().to_string()];
+    if let Some(p) = &c.phone {
+        parts.push(p.clone());
+    }
+    if !c.tags.is_empty() {
+        parts.push(format!("[{}]", c.tags.join(", ")));
+    }
+    for field in fields {
+        let value = c.address.as_ref().and_then(|a| match field {
+            ListField::City => a.city.as_deref(),
+            ListField::Country => a.country.as_deref(),
+        });
+        parts.push(value.unwrap_or("-").to_string());
+    }
+    parts.join(separator)
+}
+
+/// Expand `\t`, `\n` and `\\` so a tab separator can be passed without
+/// shell quoting tricks.
+fn unescape_separator(s: &str) -> Result<String, String> {
+    let mut out = String::new();
+    let mut chars = s.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            out.push(ch);
+            continue;
         }
-        if let Some(p) = phone {
-            if p.len() > 50 {
-                return Err(anyhow!("phone too long (max 50 chars)"));
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('\\') => out.push('\\'),
+            other => {
+                return Err(format!(
+                    "unsupported escape '\\{}' (use \\t, \\n or \\\\)",
+                    other.map(String::from).unwrap_or_default()
+                ))
             }
         }
+    }
+    Ok(out)
+}
+
+fn print_details(out: &mut Printer, c: &Contact, with_age: bool) -> Result<()> {
+    let opt = |v: Option<String>| v.unwrap_or_else(|| "-".to_string());
+    let rows = [
+        ("ID:", c.id.clone()),
+        ("Name:", out.name(&c.name)),
+        ("Email:", c.emails.join(", ")),
+        ("Phone:", opt(c.phone.clone())),
+        ("Tags:", c.tags.join(", ")),
+        ("Created:", opt(c.created_at.map(|t| t.to_rfc3339()))),
+        ("Updated:", opt(c.updated_at.map(|t| t.to_rfc3339()))),
+        (
+            "Birthday:",
+            opt(c.birthday.map(|d| match c.age().filter(|_| with_age) {
+                Some(age) => format!("{} (age {})", d, age),
+                None => d.to_string(),
+            })),
+        ),
+        ("Anniversary:", opt(c.anniversary.map(|d| d.to_string()))),
+        ("Timezone:", opt(c.timezone.clone())),
+        ("Company:", opt(c.company.clone())),
+    ];
+    for (label, value) in rows {
+        writeln!(out, "{} {}", out.label(&format!("{:<12}", label)), value)?;
+    }
+    writeln!(out, "{}", out.label("Social:"))?;
+    for (platform, handle) in &c.social_handles {
+        writeln!(out, "  {}: {}", platform, handle)?;
+    }
+    let address = c.address.clone().unwrap_or_default();
+    writeln!(out, "{}", out.label("Address:"))?;
+    let rows = [
+        ("Street:", address.street),
+        ("City:", address.city),
+        ("State:", address.state),
+        ("Postal code:", address.postal_code),
+        ("Country:", address.country),
+    ];
+    for (label, value) in rows {
+        writeln!(
+            out,
+            "  {} {}",
+            out.label(&format!("{:<12}", label)),
+            opt(value)
+        )?;
+    }
+    Ok(())
+}
+
+/// The contact `init --template` writes: obviously fake, but with enough
+/// fields filled in to show the file format.
+fn example_contact() -> Result<Contact> {
+    let mut c = Contact::new("Example Person", "someone@example.com", Some("+1 555 0100"))?;
+    c.id = "00000000-0000-0000-0000-000000000000".to_string();
+    c.tags.push("example".to_string());
+    c.birthday = NaiveDate::from_ymd_opt(1990, 1, 31);
+    c.timezone = Some("Europe/London".to_string());
+    c.address = Some(Address {
+        street: Some("1 Example Street".to_string()),
+        city: Some("Exampleton".to_string()),
+        state: None,
+        postal_code: Some("EX1 2MP".to_string()),
+        country: Some("United Kingdom".to_string()),
+    });
+    c.social_handles
+        .insert("github".to_string(), "example".to_string());
+    Ok(c)
+}
+
+/// Write `contacts` in pages of `page_size`, each a complete export (CSV
+/// and TSV files all get the header). `base` is a directory, for
+/// `base/contacts_001.csv`, or a path prefix, for `base_001.csv`. Returns
+/// the files written.
+fn export_pages(
+    contacts: &[&Contact],
+    format: ExportFormat,
+    fields: &[String],
+    json_indent: Option<usize>,
+    base: &Path,
+    page_size: usize,
+) -> Result<Vec<PathBuf>> {
+    let prefix = if base.is_dir() {
+        base.join("contacts")
+    } else {
+        base.to_path_buf()
+    };
+    let mut files = Vec::new();
+    for (i, page) in contacts.chunks(page_size).enumerate() {
+        let mut name = prefix.clone().into_os_string();
+        name.push(format!("_{:03}.{}", i + 1, format.extension()));
+        let path = PathBuf::from(name);
+        let file = create_private(&path, false)?;
+        export_formatted(
+            page,
+            format,
+            fields,
+            json_indent,
+            std::io::BufWriter::new(file),
+        )?;
+        files.push(path);
+    }
+    Ok(files)
+}
+
+/// [`export_contacts`], with JSON laid out as `export --compact/--indent` asked.
+fn export_formatted(
+    contacts: &[&Contact],
+    format: ExportFormat,
+    fields: &[String],
+    json_indent: Option<usize>,
+    out: impl Write,
+) -> Result<()> {
+    if format == ExportFormat::Json {
+        export_json(contacts, fields, json_indent, out)
+    } else {
+        export_contacts(contacts, format, fields, out)
+    }
+}
 
-        Ok(Contact {
-            id: Uuid::new_v4().to_string(),
-            name: name.trim().to_string(),
-            email: email.trim().to_string(),
-            phone: phone.map(|s| s.trim().to_string()),
+/// A group value made safe to use as a file name: path separators, `:` and
+/// whitespace become `_`, and `.`/`..` can't escape the directory.
+fn group_file_stem(value: &str) -> String {
+    let stem: String = value
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '_',
+            c if c.is_whitespace() => '_',
+            c => c,
         })
+        .collect();
+    if stem.chars().all(|c| c == '.') {
+        "_".repeat(stem.len().max(1))
+    } else {
+        stem
     }
 }
 
-#[derive(Debug, Default)]
-struct Store {
-    contacts: Vec<Contact>,
-    path: PathBuf,
-    // We keep the file handle locked during operations that require a lock.
-    // The handle is not stored persistently; locking operations open/lock/close on demand.
-}
-
-impl Store {
-    fn open(path: impl AsRef<Path>) -> Result<Self> {
-        let path = path.as_ref().to_path_buf();
-        let contacts = if path.exists() {
-            let file = OpenOptions::new()
-                .read(true)
-                .open(&path)
-                .with_context(|| format!("opening data file: {}", path.display()))?;
-            // Lock for reading to prevent simultaneous writes while reading
-            file.lock_shared()
-                .with_context(|| "acquiring shared lock for read")?;
-
-            let mut buf = String::new();
-            // Read while locked
-            let mut reader = file;
-            reader
-                .read_to_string(&mut buf)
-                .with_context(|| "reading data file")?;
-            // Parse JSON
-            let contacts: Vec<Contact> = serde_json::from_str(&buf)
-                .map_err(|e| anyhow!("failed to parse JSON: {}", e))?;
-            contacts
+/// Create (or truncate, or append to) an output file readable only by the
+/// owner, since exports contain the same personal data as the store itself.
+fn create_private(path: &std::path::Path, append: bool) -> Result<std::fs::File> {
+    let mut opts = std::fs::OpenOptions::new();
+    opts.create(true);
+    if append {
+        opts.append(true);
+    } else {
+        opts.write(true).truncate(true);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+    opts.open(path)
+        .with_context(|| format!("creating output file {}", path.display()))
+}
+
+/// Failures (including "not found") go to stderr with exit code 1, so
+/// scripts can rely on the exit status; successes print to stdout.
+fn main() {
+    if let Err(e) = try_main() {
+        eprintln!("Error: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+fn try_main() -> Result<()> {
+    let cli = Cli::parse();
+    let scheme = match cli.color_scheme {
+        Some(scheme) => scheme,
+        None => match Profiles::from_env() {
+            Ok(profiles) => profiles.color_scheme()?.unwrap_or_default(),
+            Err(_) => ColorScheme::default(),
+        },
+    };
+    let use_color = should_use_color(match (cli.color, cli.no_color) {
+        (true, _) => Some(true),
+        (_, true) => Some(false),
+        _ => None,
+    });
+    let color = if use_color { scheme } else { ColorScheme::None };
+
+    let command = with_grep(cli.grep, cli.command)
+        .unwrap_or_else(|(kind, message)| Cli::command().error(kind, message).exit());
+
+    if let Commands::Version = command {
+        let mut stdout = std::io::stdout().lock();
+        return print_version(&mut Printer::new(&mut stdout, color));
+    }
+    if let Commands::Profile { action } = command {
+        return profile(&Profiles::from_env()?, action);
+    }
+
+    let file = match (cli.profile, cli.file) {
+        (Some(name), _) => Profiles::from_env()?.path(&name)?,
+        (None, Some(file)) => file,
+        (None, None) => match std::env::var_os("CONTACTS_FILE").filter(|f| !f.is_empty()) {
+            Some(file) => PathBuf::from(file),
+            None => default_data_file()?,
+        },
+    };
+
+    // `--file -`: read the data from stdin and write any changes to stdout.
+    if file.as_os_str() == "-" {
+        if matches!(
+            command,
+            Commands::Batch
+                | Commands::Watch { .. }
+                | Commands::Init { .. }
+                | Commands::ImportWatch { .. }
+        ) {
+            return Err(anyhow!(
+                "batch, watch, init and import-watch cannot be used with --file -"
+            ));
+        }
+        if cli.format.is_some_and(|f| f != Format::Json) {
+            return Err(anyhow!("--file - only supports JSON"));
+        }
+        let mut store = Store::from_reader(std::io::stdin().lock())?;
+        let mut messages = Vec::new();
+        let outcome = run(command, &mut store, &mut Printer::new(&mut messages, color))?;
+        if outcome.changed {
+            // stdout carries the data, so messages move to stderr.
+            std::io::stderr().write_all(&messages)?;
+            store.write_json(std::io::stdout().lock())?;
+            println!();
         } else {
-            Vec::new()
-        };
+            std::io::stdout().write_all(&messages)?;
+        }
+        return outcome.into_result();
+    }
+
+    let data_path = file.canonicalize().unwrap_or(file);
+
+    if let Commands::Watch { interval } = command {
+        return watch(&data_path, Duration::from_millis(interval), color);
+    }
+    if let Commands::Init { force, template } = command {
+        if data_path.exists() && !force {
+            return Err(anyhow!(
+                "{} already exists (use --force to overwrite it)",
+                data_path.display()
+            ));
+        }
+        let format = cli.format.unwrap_or_else(|| Format::from_path(&data_path));
+        let mut store = Store::empty(&data_path, format);
+        if template {
+            store.add(example_contact()?);
+        }
+        store.save()?;
+        println!("Created {}", data_path.display());
+        return Ok(());
+    }
 
-        Ok(Store { contacts, path })
+    // Settings from the config file, when there is one.
+    let profiles = Profiles::from_env().ok();
+    let config_id_format = match &profiles {
+        Some(p) => p.id_format()?,
+        None => None,
+    };
+    let options = StoreOptions {
+        format: cli.format,
+        skip_verify: cli.skip_verify,
+        max_file_size: Some(cli.max_file_size),
+        tombstone_ttl_days: Some(cli.tombstone_ttl_days).filter(|&days| days > 0),
+        no_lock: cli.no_lock || profiles.map_or(Ok(false), |p| p.no_lock())?,
+        // Like `Store::open_existing` for commands that only read, and
+        // `Store::open_or_create` for the rest.
+        must_exist: command.is_read_only(),
+        id_format: cli.id_format.or(config_id_format).unwrap_or_default(),
+    };
+    if options.no_lock {
+        eprintln!("Warning: file locking is disabled; concurrent writes may corrupt the data file");
     }
 
-    fn list(&self) -> &[Contact] {
-        &self.contacts
+    if let Commands::ImportWatch {
+        dir,
+        format,
+        interval_ms,
+    } = command
+    {
+        let interval = Duration::from_millis(interval_ms);
+        return import_watch(&data_path, &options, &dir, format, interval, color);
     }
 
-    fn add(&mut self, c: Contact) {
-        self.contacts.push(c);
+    let (mut store, warnings) = Store::open_with(&data_path, &options)?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    if store.auto_purged() > 0 && cli.verbose {
+        eprintln!(
+            "Purged {} contact(s) archived more than {} days ago.",
+            store.auto_purged(),
+            cli.tombstone_ttl_days
+        );
     }
 
-    fn remove(&mut self, id: &str) -> bool {
-        let before = self.contacts.len();
-        self.contacts.retain(|c| c.id != id);
-        before != self.contacts.len()
+    let mut stdout = std::io::stdout().lock();
+    let outcome = run(command, &mut store, &mut Printer::new(&mut stdout, color))?;
+    drop(stdout);
+    if outcome.changed {
+        store.save()?;
+        println!("Saved.");
+    } else if cli.verbose {
+        println!("Nothing to save.");
     }
 
-    fn find(&self, q: &str) -> Vec<&Contact> {
-        let q_lower = q.to_lowercase();
-        self.contacts
-            .iter()
-            .filter(|c| {
-                c.name.to_lowercase().contains(&q_lower)
-                    || c.email.to_lowercase().contains(&q_lower)
+    outcome.into_result()
+}
+
+/// What a top-level command did: whether the store needs saving, and how
+/// many batch lines failed (their changes are still saved).
+struct Outcome {
+    changed: bool,
+    failures: usize,
+}
+
+impl Outcome {
+    fn into_result(self) -> Result<()> {
+        match self.failures {
+            0 => Ok(()),
+            n => Err(anyhow!("{} batch command(s) failed", n)),
+        }
+    }
+}
+
+/// Dispatch a top-level command.
+fn run(command: Commands, store: &mut Store, out: &mut Printer) -> Result<Outcome> {
+    match command {
+        Commands::Batch => {
+            let summary = run_batch(std::io::stdin().lock(), store, out)?;
+            writeln!(
+                out,
+                "Batch: {} succeeded, {} failed",
+                summary.succeeded, summary.failed
+            )?;
+            Ok(Outcome {
+                changed: summary.changed,
+                failures: summary.failed,
             })
-            .collect()
+        }
+        Commands::Pipeline { commands } => Ok(Outcome {
+            changed: run_pipeline(&commands, store, out)?,
+            failures: 0,
+        }),
+        command => Ok(Outcome {
+            changed: execute(command, store, out)?,
+            failures: 0,
+        }),
     }
+}
 
-    /// Persist data atomically and securely.
-    fn save(&self) -> Result<()> {
-        // 1. Make sure the parent directory exists
-        if let Some(parent) = self.path.parent() {
-            fs::create_dir_all(parent)
-                .with_context(|| format!("creating parent dir {}", parent.display()))?;
-        }
-
-        // 2. Open (or create) the target file so we can lock it.
-        //    fs2 requires a File handle to apply the lock.
-        let target_file = OpenOptions::new()
-            .read(true)
-            .write(true)
-            .create(true)
-            .open(&self.path)
-            .with_context(|| format!("opening/creating target file {}", self.path.display()))?;
-
-        // 3. Acquire an exclusive lock on the file
-        //    (prevents other processes from writing at the same time).
-        target_file
-            .lock_exclusive()
-            .with_context(|| "acquiring exclusive lock for write")?;
-
-        // 4. IMPORTANT: release the file handle and its lock before persisting.
-        //    On Windows, you cannot rename/overwrite a locked file.
-        drop(target_file);
-
-        // 5. Create a secure temporary file in the same directory.
-        //    This ensures atomic save: we write everything to the temp file first.
-        let parent = self
-            .path
-            .parent()
-            .map(Path::to_path_buf)
-            .unwrap_or_else(|| PathBuf::from("."));
-
-        let mut tmp = NamedTempFile::new_in(&parent)
-            .with_context(|| "creating secure temporary file for atomic write")?;
-
-        // 6. Serialize contacts to JSON (pretty format).
-        let j = serde_json::to_vec_pretty(&self.contacts)
-            .with_context(|| "serializing contacts to JSON")?;
-
-        // 7. Write the JSON into the temporary file.
-        tmp.write_all(&j)
-            .with_context(|| "writing JSON to temp file")?;
-
-        // 8. Ensure data is written from buffer to disk.
-        tmp.flush().with_context(|| "flushing temp file")?;
-
-        // 9. On Unix: set file permissions to 600 (owner read/write only).
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o600))
-                .with_context(|| "setting secure permissions on temp file")?;
-        }
-
-        // 10. Sync file to disk to ensure durability.
-        tmp.as_file()
-            .sync_all()
-            .with_context(|| "syncing temp file to disk")?;
-
-        // 11. Atomically replace the original file with the temp file.
-        //     At this point, the original file is unlocked, so Windows won’t complain.
-        tmp.persist(&self.path)
-            .map_err(|e| anyhow!("failed to persist temp file: {}", e))?;
+fn no_such_contact(id: &str) -> anyhow::Error {
+    anyhow!("No contact with id {}", id)
+}
 
-        Ok(())
+/// The contact whose id is `id` or, failing that, the only one whose id
+/// starts with it. When the prefix is ambiguous the candidates are listed
+/// (on a terminal) and the command fails.
+fn resolve_contact<'a>(store: &'a Store, out: &mut Printer, id: &str) -> Result<&'a Contact> {
+    if let Some(c) = store.find_by_id(id) {
+        return Ok(c);
+    }
+    match store.find_by_id_prefix(id).as_slice() {
+        [] => Err(no_such_contact(id)),
+        [c] => Ok(c),
+        found => {
+            if std::io::stdout().is_terminal() {
+                for c in found {
+                    writeln!(out, "{}", list_line(out, c, &[], " | "))?;
+                }
+            }
+            Err(anyhow!(
+                "id prefix '{}' matches {} contacts; use more characters",
+                id,
+                found.len()
+            ))
+        }
     }
 }
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-
-    let data_path = {
-        let p = cli.file;
-        let s = p.canonicalize().unwrap_or_else(|_| p.clone());
-        s
-    };
 
-    let mut store = Store::open(&data_path)?;
+/// The full id for `id`, which may be any unique prefix of one.
+fn resolve_id(store: &Store, out: &mut Printer, id: &str) -> Result<String> {
+    resolve_contact(store, out, id).map(|c| c.id.clone())
+}
 
-    match cli.command {
-        Commands::Add { name, email, phone } => {
-            let c = Contact::new(&name, &email, phone.as_deref())?;
-            println!("Adding contact: {} <{}>", c.name, c.email);
+/// Run one command against the in-memory store. Returns whether the store
+/// was modified (the caller decides when to save).
+fn execute(command: Commands, store: &mut Store, out: &mut Printer) -> Result<bool> {
+    let mut changed = false;
+    match command {
+        Commands::Add {
+            name,
+            email,
+            phone,
+            extra_emails,
+            tags,
+            birthday,
+            anniversary,
+            timezone,
+            company,
+            address,
+            social,
+            with_id,
+        } => {
+            let mut c = match &with_id {
+                Some(id) => Contact::new_with_id(&name, &email, phone.as_deref(), id)?,
+                None => {
+                    Contact::new_with_id_format(&name, &email, phone.as_deref(), store.id_format())?
+                }
+            };
+            if store.find_by_id(&c.id).is_some() {
+                let hint = if with_id.is_some() {
+                    ""
+                } else {
+                    " (pick another --id-format)"
+                };
+                return Err(anyhow!("id {} is already in use{}", c.id, hint));
+            }
+            c.apply(ContactUpdate {
+                extra_emails,
+                timezone,
+                company,
+                address: address.into_address(),
+                social_handles: social.into_iter().collect(),
+                ..Default::default()
+            })?;
+            for t in &tags {
+                c.add_tag(t)?;
+            }
+            c.birthday = birthday;
+            c.anniversary = anniversary;
+            writeln!(out, "Adding contact: {} <{}>", c.name, c.primary_email())?;
+            let (id, summary) = (c.id.clone(), format!("{} <{}>", c.name, c.primary_email()));
             store.add(c);
-            store.save()?;
-            println!("Saved.");
+            store.record_audit(AuditOperation::Add, Some(&id), summary);
+            changed = true;
         }
-        Commands::Remove { id } => {
+        Commands::Update {
+            id,
+            name,
+            email,
+            phone,
+            timezone,
+            company,
+            social,
+        } => {
+            let update = ContactUpdate {
+                name,
+                email,
+                phone,
+                timezone,
+                company,
+                social_handles: social.into_iter().collect(),
+                ..Default::default()
+            };
+            let id = resolve_id(store, out, &id)?;
+            if store.update_contact(&id, update)? {
+                store.record_audit(AuditOperation::Update, Some(&id), "updated");
+                changed = true;
+                writeln!(out, "Updated contact {}", id)?;
+            } else {
+                return Err(no_such_contact(&id));
+            }
+        }
+        Commands::Rename { id, new_name } => {
+            if store.find_by_id_prefix(&id).is_empty() {
+                return Err(anyhow!(
+                    "Contact '{}' not found; try 'contacts list' to see valid IDs.",
+                    id
+                ));
+            }
+            let id = resolve_id(store, out, &id)?;
+            let summary = format!("renamed to {}", new_name);
+            let update = ContactUpdate {
+                name: Some(new_name),
+                ..Default::default()
+            };
+            store.update_contact(&id, update)?;
+            store.record_audit(AuditOperation::Update, Some(&id), summary);
+            changed = true;
+            writeln!(out, "Renamed contact {}", id)?;
+        }
+        Commands::Remove { id, confirm, yes } => {
+            let c = resolve_contact(store, out, &id)?;
+            let id = c.id.clone();
+            if confirm && !yes {
+                let prompt = format!("Remove {} <{}>? [y/N]: ", c.name, c.primary_email());
+                let proceed =
+                    ask_yes_no(out, &prompt).context("pass --yes to remove without a prompt")?;
+                if !proceed {
+                    writeln!(out, "Aborted.")?;
+                    return Ok(false);
+                }
+            }
+            let summary = format!("{} <{}>", c.name, c.primary_email());
             if store.remove(&id) {
-                store.save()?;
-                println!("Removed contact {}", id);
+                store.record_audit(AuditOperation::Remove, Some(&id), summary);
+                changed = true;
+                writeln!(out, "Removed contact {}", id)?;
             } else {
-                println!("No contact with id {}", id);
-            }
-        }
-        Commands::List => {
-            for c in store.list() {
-                println!(
-                    "{} | {} | {}{}",
-                    c.id,
-                    c.name,
-                    c.email,
-                    c.phone
-                        .as_ref()
-                        .map(|p| format!(" | {}", p))
-                        .unwrap_or_default()
-                );
+                return Err(no_such_contact(&id));
+            }
+        }
+        Commands::Unremove { id } => {
+            store.unremove(&id)?;
+            store.record_audit(AuditOperation::Add, Some(&id), "restored");
+            changed = true;
+            writeln!(out, "Restored contact {}", id)?;
+        }
+        Commands::Archive { id } => {
+            let id = resolve_id(store, out, &id)?;
+            if store.set_archived(&id, true) {
+                store.record_audit(AuditOperation::Update, Some(&id), "archived");
+                changed = true;
+                writeln!(out, "Archived contact {}", id)?;
+            } else {
+                return Err(no_such_contact(&id));
+            }
+        }
+        Commands::Unarchive { id } => {
+            let id = resolve_id(store, out, &id)?;
+            if store.set_archived(&id, false) {
+                store.record_audit(AuditOperation::Update, Some(&id), "unarchived");
+                changed = true;
+                writeln!(out, "Unarchived contact {}", id)?;
+            } else {
+                return Err(no_such_contact(&id));
             }
-            println!("Total: {}", store.list().len());
         }
-        Commands::Find { query } => {
-            let found = store.find(&query);
+        Commands::Show { id, with_age } => {
+            let c = resolve_contact(store, out, &id)?;
+            print_details(out, c, with_age)?;
+        }
+        Commands::Card { id } => {
+            let c = resolve_contact(store, out, &id)?;
+            writeln!(out, "{}", c.display_card())?;
+        }
+        Commands::Resolve { email, json } => match store.find_by_email(&email) {
+            Some(c) if json => writeln!(out, "{}", c.to_json())?,
+            Some(c) => print_details(out, c, false)?,
+            None => return Err(anyhow!("No contact with email {}", email)),
+        },
+        Commands::List {
+            fields,
+            include_archived,
+            since,
+            before,
+            output_separator,
+            with_age,
+            after_id,
+            limit,
+            sort_by,
+            reverse,
+        } => {
+            if after_id.is_some() || limit.is_some() {
+                let (page, next) =
+                    store.paginate_with_cursor(after_id.as_deref(), limit.unwrap_or(20));
+                for c in page {
+                    writeln!(out, "{}", list_line(out, c, &fields, &output_separator))?;
+                }
+                if let Some(next) = next {
+                    writeln!(out, "Next: --after-id {}", next)?;
+                }
+                return Ok(false);
+            }
+            let shown: Vec<_> = store
+                .sorted_list(sort_by, reverse)
+                .into_iter()
+                .filter(|c| c.created_between(since, before))
+                .filter(|c| include_archived || !c.archived)
+                .collect();
+            for c in &shown {
+                let mut line = list_line(out, c, &fields, &output_separator);
+                if let Some(age) = c.age().filter(|_| with_age) {
+                    line.push_str(&format!(" (age {})", age));
+                }
+                writeln!(out, "{}", line)?;
+            }
+            if shown.is_empty() && store.is_empty() {
+                writeln!(out, "Address book is empty.")?;
+            } else {
+                let counts: Counts = shown.iter().copied().collect();
+                writeln!(out, "Total: {}", counts)?;
+            }
+        }
+        Commands::Head(args) => args.print(store, out, false)?,
+        Commands::Tail(args) => args.print(store, out, true)?,
+        Commands::Random { n, seed } => {
+            let mut rng = match seed {
+                Some(seed) => StdRng::seed_from_u64(seed),
+                None => StdRng::from_entropy(),
+            };
+            for c in store.sample(n, &mut rng) {
+                writeln!(out, "{}", list_line(out, c, &[], " | "))?;
+            }
+        }
+        Commands::Find(args) if args.first => {
+            let query = args.query.as_deref().unwrap_or_default();
+            let c = store
+                .find_first(query)
+                .ok_or_else(|| anyhow!("no contact matches '{}'", query))?;
+            writeln!(out, "{}", find_line(c, &args.exclude_fields))?;
+        }
+        Commands::Find(args) => {
+            let found = args.matches(store);
             for c in &found {
-    println!("{} - {}", c.name, c.phone.as_deref().unwrap_or("No phone"));
+                writeln!(out, "{}", find_line(c, &args.exclude_fields))?;
+            }
+            writeln!(out, "Found: {}", found.len())?;
+        }
+        Commands::Search {
+            query,
+            include_archived,
+            phone_search,
+        } => {
+            let args = FindArgs {
+                include_archived,
+                phone_search,
+                ..FindArgs::for_query(query)
+            };
+            return execute(Commands::Find(args), store, out);
+        }
+        Commands::Suggest { query } => {
+            let domain = match query.rsplit_once('@') {
+                Some((_, domain)) => domain,
+                None => query.as_str(),
+            };
+            let mut found: Vec<&Contact> = store
+                .find_by_email_domain(domain)
+                .into_iter()
+                .filter(|c| !c.archived && !c.primary_email().eq_ignore_ascii_case(&query))
+                .collect();
+            found.sort_by_cached_key(|c| c.name.to_lowercase());
+            for c in found.iter().take(5) {
+                writeln!(out, "{}", list_line(out, c, &[], " | "))?;
+            }
+            writeln!(out, "Suggestions: {}", found.len().min(5))?;
+        }
+        Commands::MissingPhone => {
+            let found = store.find_without_phone();
+            for c in &found {
+                writeln!(out, "{}", list_line(out, c, &[], " | "))?;
+            }
+            writeln!(out, "Found: {}", found.len())?;
+        }
+        Commands::MissingEmail => {
+            let found = store.find_without_email();
+            for c in &found {
+                writeln!(out, "{}", list_line(out, c, &[], " | "))?;
+            }
+            writeln!(out, "Found: {}", found.len())?;
+        }
+        Commands::Upcoming { month } => {
+            let month = month.unwrap_or_else(|| Utc::now().month());
+            let events = store.upcoming_events_in_month(month);
+            for e in &events {
+                writeln!(
+                    out,
+                    "{} | {} | {} <{}>",
+                    e.kind,
+                    e.date,
+                    e.contact.name,
+                    e.contact.primary_email()
+                )?;
+            }
+            writeln!(out, "Upcoming: {}", events.len())?;
+        }
+        Commands::Export {
+            format,
+            output,
+            fields,
+            append,
+            ids,
+            since,
+            template,
+            group_by,
+            output_dir,
+            paginate,
+            page_size,
+            pretty: _,
+            compact,
+            indent,
+        } => {
+            if compact && format != ExportFormat::Json {
+                return Err(anyhow!("--compact only applies to JSON exports"));
+            }
+            let json_indent = (!compact).then_some(indent);
+            if let (Some(field), Some(dir)) = (group_by, output_dir) {
+                std::fs::create_dir_all(&dir)
+                    .with_context(|| format!("creating {}", dir.display()))?;
+                let groups = store.group_by_field(field);
+                for (value, contacts) in &groups {
+                    let name = format!("{}.{}", group_file_stem(value), format.extension());
+                    let file = create_private(&dir.join(name), false)?;
+                    let w = std::io::BufWriter::new(file);
+                    export_formatted(contacts, format, &fields, json_indent, w)?;
+                }
+                writeln!(out, "Exported {} groups to {}", groups.len(), dir.display())?;
+                return Ok(false);
+            }
+            if template {
+                if format != ExportFormat::Csv {
+                    return Err(anyhow!("--template only applies to CSV exports"));
+                }
+                let header = ContactField::ALL.map(ContactField::as_str).join(",");
+                match output {
+                    Some(path) => {
+                        writeln!(create_private(&path, false)?, "{}", header)?;
+                        writeln!(out, "Wrote CSV template to {}", path.display())?;
+                    }
+                    None => writeln!(out, "{}", header)?,
+                }
+                return Ok(false);
+            }
+            let contacts: Vec<&Contact> = if let Some(since) = since {
+                store.find_since(since)
+            } else if ids.is_empty() {
+                store.contacts().iter().collect()
+            } else {
+                let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+                store.find_by_ids(&ids)
+            };
+            if let (true, Some(base)) = (paginate, &output) {
+                let files = export_pages(
+                    &contacts,
+                    format,
+                    &fields,
+                    json_indent,
+                    base,
+                    page_size as usize,
+                )?;
+                writeln!(
+                    out,
+                    "Exported {} contacts to {} files",
+                    contacts.len(),
+                    files.len()
+                )?;
+                return Ok(false);
+            }
+            match output {
+                Some(path) => {
+                    if append && format == ExportFormat::Json {
+                        return Err(anyhow!("--append cannot be used with JSON exports"));
+                    }
+                    let file = create_private(&path, append)?;
+                    let fresh = file.metadata()?.len() == 0;
+                    let w = std::io::BufWriter::new(file);
+                    if append && !fresh {
+                        export_rows(&contacts, format, &fields, w)?;
+                    } else {
+                        export_formatted(&contacts, format, &fields, json_indent, w)?;
+                    }
+                    writeln!(
+                        out,
+                        "Exported {} contacts to {}",
+                        contacts.len(),
+                        path.display()
+                    )?;
+                }
+                None => export_formatted(&contacts, format, &fields, json_indent, &mut *out)?,
+            }
+        }
+        Commands::Import {
+            file,
+            dedupe,
+            overwrite,
+            quiet,
+            format,
+            mappings,
+            ignore_errors,
+        } => {
+            let format = match format {
+                Some(format) => format,
+                None => ImportFormat::detect(&file)?,
+            };
+            if format != ImportFormat::Csv && !mappings.is_empty() {
+                return Err(anyhow!("--map only applies to CSV imports"));
+            }
+            let progress = if quiet || !std::io::stdout().is_terminal() {
+                ProgressBar::hidden()
+            } else {
+                ProgressBar::new(0).with_style(ProgressStyle::with_template(
+                    "Importing contacts: {pos}/{len} {wide_bar}",
+                )?)
+            };
+            let read = read_import_file(&file, format, &mappings, &progress);
+            progress.finish_and_clear();
+            let (incoming, errors) = read?;
+            if let Some(first) = errors.first().filter(|_| !ignore_errors) {
+                return Err(anyhow!(
+                    "{} in {}; nothing imported (use --ignore-errors to import the valid rows)",
+                    first,
+                    file.display()
+                ));
+            }
+            let result = if dedupe {
+                store.import_deduped(incoming, overwrite)
+            } else {
+                store.import_all(incoming)
+            };
+            changed = result.imported + result.updated > 0;
+            if changed {
+                let summary = format!(
+                    "imported {}, updated {} from {}",
+                    result.imported,
+                    result.updated,
+                    file.display()
+                );
+                store.record_audit(AuditOperation::Import, None, summary);
+            }
+            writeln!(
+                out,
+                "Imported {}, skipped {} existing, updated {}",
+                result.imported, result.skipped, result.updated
+            )?;
+            if !errors.is_empty() {
+                writeln!(out, "Failed {}:", errors.len())?;
+                writeln!(out, "{:>6}  Error", "Row")?;
+                for e in &errors {
+                    writeln!(out, "{:>6}  {}", e.row, e.message)?;
+                }
+            }
+        }
+        Commands::Diff { against } => {
+            let (other, warnings) = Store::open(&against)?;
+            for warning in &warnings {
+                eprintln!("Warning: {}: {}", against.display(), warning);
+            }
+            write!(out, "{}", store.diff_with(&other))?;
+        }
+        Commands::Patch { patch_file } => {
+            let raw = std::fs::read(&patch_file)
+                .with_context(|| format!("reading patch file {}", patch_file.display()))?;
+            let patch = serde_json::from_slice(&raw)
+                .with_context(|| format!("parsing patch file {}", patch_file.display()))?;
+            store.apply_patch(patch)?;
+            let summary = format!("applied patch {}", patch_file.display());
+            store.record_audit(AuditOperation::Update, None, summary);
+            changed = true;
+            writeln!(out, "Patched. Total: {}", store.contacts().len())?;
+        }
+        Commands::Batch => {
+            return Err(anyhow!("batch cannot be nested"));
+        }
+        Commands::Pipeline { .. } => {
+            return Err(anyhow!("pipeline cannot be nested"));
+        }
+        Commands::Watch { .. } => {
+            return Err(anyhow!("watch cannot be used inside batch"));
+        }
+        Commands::Init { .. } => {
+            return Err(anyhow!("init cannot be used inside batch"));
+        }
+        Commands::ImportWatch { .. } => {
+            return Err(anyhow!("import-watch cannot be used inside batch"));
+        }
+        Commands::Profile { .. } => {
+            return Err(anyhow!("profile cannot be used inside batch"));
+        }
+        Commands::Version => print_version(out)?,
+        Commands::Anonymize { output } => {
+            if output.exists() {
+                return Err(anyhow!("{} already exists", output.display()));
+            }
+            let copy = store.anonymized(&output);
+            copy.save()?;
+            writeln!(
+                out,
+                "Wrote {} anonymized contacts to {}",
+                copy.total_len(),
+                output.display()
+            )?;
+        }
+        Commands::Analyze { field } => {
+            let mut words: Vec<(String, usize)> = store.word_frequency(field).into_iter().collect();
+            // Most frequent first; the map already sorted ties alphabetically.
+            words.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+            for (word, count) in words.iter().take(20) {
+                writeln!(out, "{:>5}  {}", count, word)?;
+            }
+            if words.is_empty() {
+                writeln!(out, "No words in {}.", field.as_str())?;
+            }
+        }
+        Commands::Validate => {
+            let errors = store.validate_all();
+            for (id, e) in &errors {
+                writeln!(out, "{}: {:#}", id, e)?;
+            }
+            if !errors.is_empty() {
+                return Err(anyhow!("{} invalid contact(s)", errors.len()));
+            }
+            writeln!(out, "All {} contacts are valid.", store.total_len())?;
+        }
+        Commands::Lint { allowed_tags } => {
+            let allowed: Vec<&str> = allowed_tags.iter().map(String::as_str).collect();
+            let warnings = store.lint(&allowed);
+            for w in &warnings {
+                writeln!(out, "WARNING: {}", w)?;
+            }
+            if !warnings.is_empty() {
+                return Err(anyhow!("{} issue(s) found", warnings.len()));
+            }
+            writeln!(out, "No issues found.")?;
+        }
+        Commands::AuditLog { tail } => {
+            let log = store.audit_log()?;
+            let skip = tail.map_or(0, |n| log.len().saturating_sub(n));
+            for entry in &log[skip..] {
+                writeln!(out, "{}", entry)?;
+            }
+        }
+        Commands::Tag { tag, ids } => {
+            let ids = ids
+                .iter()
+                .map(|id| resolve_id(store, out, id))
+                .collect::<Result<Vec<_>>>()?;
+            let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+            let n = store.bulk_tag(&ids, &tag)?;
+            changed = n > 0;
+            if changed {
+                let summary = format!("added tag '{}' to {} contact(s)", tag, n);
+                store.record_audit(AuditOperation::Update, None, summary);
+            }
+            writeln!(out, "Tagged {} contact(s) with '{}'", n, tag)?;
+        }
+        Commands::RenameTag { from, to } => {
+            let n = store.rename_tag(&from, &to)?;
+            changed = n > 0;
+            if changed {
+                let summary = format!("renamed tag '{}' to '{}' on {} contact(s)", from, to, n);
+                store.record_audit(AuditOperation::Update, None, summary);
+            }
+            writeln!(
+                out,
+                "Renamed tag '{}' to '{}' on {} contact(s)",
+                from, to, n
+            )?;
+        }
+        Commands::DeleteTag { tag } => {
+            let n = store.delete_tag(&tag);
+            changed = n > 0;
+            if changed {
+                let summary = format!("deleted tag '{}' from {} contact(s)", tag, n);
+                store.record_audit(AuditOperation::Update, None, summary);
+            }
+            writeln!(out, "Deleted tag '{}' from {} contact(s)", tag, n)?;
+        }
+        Commands::Compact { defrag: false } => {
+            // The next save, including one after later batch lines, writes
+            // compact JSON.
+            store.compact_json()?;
+            changed = true;
+            writeln!(out, "Compacted.")?;
+        }
+        Commands::Compact { defrag: true } => {
+            let archived = store.archived_len();
+            let (old, new) = store.compact()?;
+            if archived > 0 {
+                let summary = format!("purged {} archived contact(s)", archived);
+                store.record_audit(AuditOperation::Remove, None, summary);
+            }
+            changed = true;
+            writeln!(out, "Compacted: {} -> {} bytes", old, new)?;
+        }
+        Commands::Reindex => {
+            // The lookups live only in memory and were just built by
+            // `open`; rebuilding them checks the file loads cleanly.
+            store.reindex();
+            store.check_id_uniqueness()?;
+            writeln!(out, "Index rebuilt: {} contacts indexed", store.total_len())?;
+        }
+        Commands::Purge { confirm } => {
+            let n = store.archived_len();
+            if n == 0 {
+                writeln!(out, "No archived contacts.")?;
+                return Ok(false);
+            }
+            if !confirm {
+                writeln!(out, "This will remove {} archived contacts.", n)?;
+                let proceed = ask_yes_no(out, "Continue? [y/N] ")
+                    .context("pass --confirm to purge without a prompt")?;
+                if !proceed {
+                    writeln!(out, "Aborted.")?;
+                    return Ok(false);
+                }
+            }
+            store.purge_archived();
+            let summary = format!("purged {} archived contact(s)", n);
+            store.record_audit(AuditOperation::Remove, None, summary);
+            changed = true;
+            writeln!(out, "Purged {} archived contact(s)", n)?;
+        }
+    }
+
+    Ok(changed)
+}
+
+/// Prompt on `out` and read a yes/no answer from the terminal. Without a
+/// terminal on stdin (scripts, `batch`, `--file -`) there is nobody to ask, so
+/// callers must offer a flag that skips the prompt.
+fn ask_yes_no(out: &mut Printer, prompt: &str) -> Result<bool> {
+    let stdin = std::io::stdin();
+    if !stdin.is_terminal() {
+        return Err(anyhow!("cannot confirm: not a terminal"));
+    }
+    read_yes_no(out, &mut stdin.lock(), prompt)
+}
+
+/// The answer defaults to no: only `y` or `yes` (any case) proceeds.
+fn read_yes_no(out: &mut Printer, input: &mut dyn BufRead, prompt: &str) -> Result<bool> {
+    write!(out, "{}", prompt)?;
+    out.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    Ok(matches!(
+        answer.trim().to_ascii_lowercase().as_str(),
+        "y" | "yes"
+    ))
 }
-            println!("Found: {}", found.len());
+
+/// Clear the terminal and print the active contacts, as `list` would.
+fn redraw(path: &Path, color: ColorScheme) -> Result<()> {
+    // `watch` already reported any warnings when the file was first opened.
+    let (store, _) = Store::open(path)?;
+    let mut stdout = std::io::stdout().lock();
+    let mut out = Printer::new(&mut stdout, color);
+    write!(out, "\x1b[2J\x1b[H")?;
+    if store.is_empty() {
+        writeln!(out, "Address book is empty.")?;
+    } else {
+        for c in store.contacts().iter().filter(|c| !c.archived) {
+            writeln!(out, "{}", list_line(&out, c, &[], " | "))?;
         }
+        writeln!(out, "Total: {}", store.len())?;
     }
+    writeln!(out, "Watching {} (Ctrl-C to exit)", path.display())?;
+    out.flush()?;
+    Ok(())
+}
 
+/// Parse and validate every row of an import file. Returns the valid
+/// contacts and an [`ImportError`] for each row that failed to parse or
+/// validate (for CSV and NDJSON; a JSON array fails as a whole); the caller
+/// decides whether any errors abort the import. A file that can't be read,
+/// or a JSON or CSV header that can't be parsed, is an error.
+///
+/// `progress` gets its length from the file's line count (for JSON, the
+/// number of contacts) and advances every [`IMPORT_CHUNK`] rows.
+fn read_import_file(
+    file: &Path,
+    format: ImportFormat,
+    mappings: &[ColumnMapping],
+    progress: &ProgressBar,
+) -> Result<(Vec<Contact>, Vec<ImportError>)> {
+    let raw =
+        std::fs::read(file).with_context(|| format!("reading import file {}", file.display()))?;
+    let lines = raw.iter().filter(|&&b| b == b'\n').count()
+        + usize::from(raw.last().is_some_and(|&b| b != b'\n'));
+    let rows: Box<dyn Iterator<Item = Result<Contact>>> = match format {
+        ImportFormat::Json => {
+            let contacts = serde_json::from_slice::<Vec<Contact>>(&raw)
+                .with_context(|| format!("parsing import file {}", file.display()))?;
+            progress.set_length(contacts.len() as u64);
+            Box::new(contacts.into_iter().map(Ok))
+        }
+        ImportFormat::Ndjson => {
+            progress.set_length(lines as u64);
+            Box::new(
+                raw.split(|&b| b == b'\n')
+                    .filter(|line| !line.trim_ascii().is_empty())
+                    .map(|line| serde_json::from_slice(line).context("parsing JSON line")),
+            )
+        }
+        ImportFormat::Csv => {
+            // The header row is not a contact.
+            progress.set_length(lines.saturating_sub(1) as u64);
+            Box::new(contacts_from_csv(raw.as_slice(), mappings)?)
+        }
+    };
+    let mut valid = Vec::new();
+    let mut errors = Vec::new();
+    let mut count = 0;
+    for (i, row) in rows.enumerate() {
+        match row.and_then(|c| c.validate_with_id().map(|()| c)) {
+            Ok(c) => valid.push(c),
+            Err(e) => errors.push(ImportError {
+                row: i + 1,
+                message: format!("{:#}", e),
+            }),
+        }
+        count = i + 1;
+        if count % IMPORT_CHUNK == 0 {
+            progress.set_position(count as u64);
+        }
+    }
+    progress.set_position(count as u64);
+    Ok((valid, errors))
+}
+
+/// Modification time and size of a file, to notice when it is rewritten.
+type FileStamp = (SystemTime, u64);
+
+/// Import every file in `dir` with `format`'s extension, skipping contacts
+/// whose email is already stored, then move the imported files to
+/// `dir/processed` (adding `.1`, `.2`, ... to the name rather than
+/// replacing an earlier file). A file that fails, e.g. because it is still
+/// being written, is reported, left where it is and recorded in `failed`;
+/// it is retried once its size or modification time changes. Returns the
+/// number of files imported.
+fn import_new_files(
+    data_path: &Path,
+    options: &StoreOptions,
+    dir: &Path,
+    format: ImportFormat,
+    failed: &mut HashMap<PathBuf, FileStamp>,
+    out: &mut Printer,
+) -> Result<usize> {
+    let extension = match format {
+        ImportFormat::Json => "json",
+        ImportFormat::Csv => "csv",
+        ImportFormat::Ndjson => "ndjson",
+    };
+    let mut new_files = Vec::new();
+    let mut present = HashSet::new();
+    for entry in std::fs::read_dir(dir).with_context(|| format!("reading {}", dir.display()))? {
+        let path = entry?.path();
+        let matches = path.is_file()
+            && path
+                .extension()
+                .is_some_and(|e| e.eq_ignore_ascii_case(extension));
+        if !matches {
+            continue;
+        }
+        // Taken before reading, so a write that finishes after the read
+        // changes the stamp and the file is tried again.
+        let meta = std::fs::metadata(&path)?;
+        let stamp = (meta.modified()?, meta.len());
+        if failed.get(&path) != Some(&stamp) {
+            new_files.push((path.clone(), stamp));
+        }
+        present.insert(path);
+    }
+    failed.retain(|path, _| present.contains(path));
+    if new_files.is_empty() {
+        return Ok(0);
+    }
+    new_files.sort();
+
+    let (mut store, warnings) = Store::open_with(data_path, options)?;
+    for warning in &warnings {
+        eprintln!("Warning: {}", warning);
+    }
+    let mut imported = Vec::new();
+    for (file, stamp) in new_files {
+        let read = read_import_file(&file, format, &[], &ProgressBar::hidden());
+        let contacts = read.and_then(|(contacts, errors)| match errors.first() {
+            Some(first) => Err(anyhow!("{}", first)),
+            None => Ok(contacts),
+        });
+        match contacts {
+            Ok(contacts) => {
+                let result = store.import_deduped(contacts, false);
+                if result.imported > 0 {
+                    let summary = format!("imported {} from {}", result.imported, file.display());
+                    store.record_audit(AuditOperation::Import, None, summary);
+                }
+                writeln!(
+                    out,
+                    "{}: imported {}, skipped {} existing",
+                    file.display(),
+                    result.imported,
+                    result.skipped
+                )?;
+                failed.remove(&file);
+                imported.push(file);
+            }
+            Err(e) => {
+                writeln!(out, "{}: {:#}", file.display(), e)?;
+                failed.insert(file, stamp);
+            }
+        }
+    }
+    store.save()?;
+
+    let processed = dir.join("processed");
+    std::fs::create_dir_all(&processed)
+        .with_context(|| format!("creating {}", processed.display()))?;
+    for file in &imported {
+        let target = unused_path(&processed, file);
+        std::fs::rename(file, &target)
+            .with_context(|| format!("moving {} to {}", file.display(), target.display()))?;
+    }
+    Ok(imported.len())
+}
+
+/// `dir/<file name>`, or `dir/<stem>.N.<ext>` with the first N that is free.
+fn unused_path(dir: &Path, file: &Path) -> PathBuf {
+    let target = dir.join(file.file_name().unwrap_or_default());
+    if !target.exists() {
+        return target;
+    }
+    let stem = file.file_stem().unwrap_or_default().to_string_lossy();
+    let ext = file
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| dir.join(format!("{}.{}{}", stem, n, ext)))
+        .find(|path| !path.exists())
+        .expect("some numbered name is free")
+}
+
+fn import_watch(
+    data_path: &Path,
+    options: &StoreOptions,
+    dir: &Path,
+    format: ImportFormat,
+    interval: Duration,
+    color: ColorScheme,
+) -> Result<()> {
+    let mut failed = HashMap::new();
+    loop {
+        let mut stdout = std::io::stdout().lock();
+        let mut out = Printer::new(&mut stdout, color);
+        if let Err(e) = import_new_files(data_path, options, dir, format, &mut failed, &mut out) {
+            eprintln!("Error: {:#}", e);
+        }
+        drop(stdout);
+        std::thread::sleep(interval);
+    }
+}
+
+/// Keep watching even if one read fails (e.g. a half-edited file).
+fn redraw_or_report(path: &Path, color: ColorScheme) {
+    if let Err(e) = redraw(path, color) {
+        eprintln!("Error: {:#}", e);
+    }
+}
+
+fn watch(path: &Path, interval: Duration, color: ColorScheme) -> Result<()> {
+    let mut watcher = FileWatcher::new(path);
+    redraw_or_report(path, color);
+
+    #[cfg(feature = "watch-native")]
+    {
+        let _ = interval;
+        watcher.watch_native(|| {
+            redraw_or_report(path, color);
+            Ok(())
+        })
+    }
+
+    #[cfg(not(feature = "watch-native"))]
+    loop {
+        if watcher.wait_for_change(interval, None) {
+            redraw_or_report(watcher.path(), color);
+        }
+    }
+}
+
+fn print_version(out: &mut Printer) -> Result<()> {
+    let rows = [
+        ("Version:", env!("CARGO_PKG_VERSION")),
+        ("Git commit:", env!("CARGO_GIT_HASH")),
+        ("Built:", env!("CARGO_BUILD_DATE")),
+    ];
+    for (label, value) in rows {
+        writeln!(out, "{} {}", out.label(&format!("{:<12}", label)), value)?;
+    }
+    Ok(())
+}
+
+fn profile(profiles: &Profiles, action: ProfileAction) -> Result<()> {
+    match action {
+        ProfileAction::Create { name } => {
+            let path = profiles.create(&name)?;
+            println!("Created profile {} ({})", name, path.display());
+        }
+        ProfileAction::Delete { name } => {
+            profiles.delete(&name)?;
+            println!("Deleted profile {}", name);
+        }
+        ProfileAction::List => {
+            let default = profiles.default_profile()?;
+            for name in profiles.list()? {
+                let mark = if default.as_deref() == Some(name.as_str()) {
+                    "*"
+                } else {
+                    " "
+                };
+                println!("{} {}", mark, name);
+            }
+        }
+        ProfileAction::Default { name } => {
+            profiles.set_default(&name)?;
+            println!("Default profile: {}", name);
+        }
+    }
     Ok(())
 }
 
+/// The default profile's file if one is configured, else `contacts.json`
+/// (in the build-time data directory, if there is one).
+fn default_data_file() -> Result<PathBuf> {
+    let fallback = match BUILD_DATA_DIR {
+        Some(dir) => Path::new(dir).join("contacts.json"),
+        None => PathBuf::from("contacts.json"),
+    };
+    // Without HOME/XDG variables there can't be a config file either.
+    let Ok(profiles) = Profiles::from_env() else {
+        return Ok(fallback);
+    };
+    match profiles.default_profile()? {
+        Some(name) => profiles.path(&name),
+        None => Ok(fallback),
+    }
+}
+
+/// Fold `--grep` into the subcommand: on its own it means `find PATTERN`,
+/// and given with `find` it is that find's query. Any other combination is
+/// a usage error.
+fn with_grep(
+    grep: Option<String>,
+    command: Option<Commands>,
+) -> Result<Commands, (ErrorKind, &'static str)> {
+    match (grep, command) {
+        (Some(query), None) => Ok(Commands::Find(FindArgs::for_query(query))),
+        (None, Some(command)) => Ok(command),
+        (Some(query), Some(Commands::Find(mut args))) if args.query.is_none() => {
+            args.query = Some(query);
+            Ok(Commands::Find(args))
+        }
+        (Some(_), Some(Commands::Find(_))) => Err((
+            ErrorKind::ArgumentConflict,
+            "--grep is the query for find; give one or the other",
+        )),
+        (Some(_), Some(_)) => Err((
+            ErrorKind::ArgumentConflict,
+            "--grep can only be combined with find",
+        )),
+        (None, None) => Err((
+            ErrorKind::MissingSubcommand,
+            "a subcommand or --grep is required",
+        )),
+    }
+}
+
+/// One batch line, parsed with the same subcommand syntax as the CLI.
+#[derive(Parser)]
+#[command(no_binary_name = true)]
+struct BatchLine {
+    /// Shorthand for `find PATTERN`, as on the command line
+    #[arg(short = 'g', long, global = true, value_name = "PATTERN")]
+    grep: Option<String>,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
+}
+
+struct BatchSummary {
+    succeeded: usize,
+    failed: usize,
+    changed: bool,
+}
+
+/// Execute each non-empty, non-`#` line of `input` against `store`. A line
+/// that fails is reported on stderr and skipped; the rest still run.
+fn run_batch(input: impl BufRead, store: &mut Store, out: &mut Printer) -> Result<BatchSummary> {
+    let mut summary = BatchSummary {
+        succeeded: 0,
+        failed: 0,
+        changed: false,
+    };
+    for (n, line) in input.lines().enumerate() {
+        let line = line.with_context(|| "reading batch input")?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let result = shlex::split(line)
+            .ok_or_else(|| anyhow!("unbalanced quotes"))
+            .and_then(parse_line)
+            .and_then(|command| execute(command, store, out));
+        match result {
+            Ok(changed) => {
+                summary.succeeded += 1;
+                summary.changed |= changed;
+            }
+            Err(e) => {
+                summary.failed += 1;
+                eprintln!("line {}: {}", n + 1, e.to_string().trim_end());
+            }
+        }
+    }
+    Ok(summary)
+}
+
+/// Parse one batch or pipeline line.
+fn parse_line(words: Vec<String>) -> Result<Commands> {
+    let parsed = BatchLine::try_parse_from(words).map_err(|e| anyhow!(e.render().to_string()))?;
+    with_grep(parsed.grep, parsed.command).map_err(|(_, message)| anyhow!(message))
+}
+
+/// Execute `commands` in order against `store`, stopping at the first error.
+/// A command containing the word `<all>` runs once per contact matched by
+/// the most recent `find`, with `<all>` replaced by its id.
+fn run_pipeline(commands: &[String], store: &mut Store, out: &mut Printer) -> Result<bool> {
+    let mut selection: Option<Vec<String>> = None;
+    let mut changed = false;
+    for line in commands {
+        let words = shlex::split(line).ok_or_else(|| anyhow!("unbalanced quotes in '{}'", line))?;
+        let runs: Vec<Vec<String>> = if words.iter().any(|w| w == "<all>") {
+            let ids = selection
+                .as_ref()
+                .ok_or_else(|| anyhow!("'<all>' in '{}' needs an earlier find", line))?;
+            ids.iter()
+                .map(|id| {
+                    words
+                        .iter()
+                        .map(|w| if w == "<all>" { id.clone() } else { w.clone() })
+                        .collect()
+                })
+                .collect()
+        } else {
+            vec![words]
+        };
+        for words in runs {
+            let command = parse_line(words)?;
+            if let Commands::Find(args) = &command {
+                selection = Some(args.matches(store).iter().map(|c| c.id.clone()).collect());
+            }
+            changed |= execute(command, store, out)
+                .with_context(|| format!("pipeline command '{}'", line))?;
+        }
+    }
+    Ok(changed)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use tempfile::tempdir;
 
     #[test]
-    fn contact_validation() {
-        assert!(Contact::new("", "a@b.com", None).is_err());
-        assert!(Contact::new("Alice", "", None).is_err());
-        let long_name = "x".repeat(201);
-        assert!(Contact::new(&long_name, "a@b.com", None).is_err());
-        let ok = Contact::new("Alice", "a@b.com", Some("1234")).unwrap();
-        assert_eq!(ok.name, "Alice");
-    }
+    fn batch_skips_failed_lines() -> Result<()> {
+        let input = "\
+add Alice alice@x.com
+add \"Bob Brown\" bob@x.com --tag work
 
-    #[test]
-    fn add_remove_persist() -> Result<()> {
-        let dir = tempdir()?;
+# comments and blank lines are ignored
+add Carol carol@x.com
+add \"\" nobody@x.com
+";
+        let dir = tempfile::tempdir()?;
         let db = dir.path().join("contacts.json");
-        let mut store = Store::open(&db)?;
-        assert_eq!(store.list().len(), 0);
-        let c = Contact::new("Bob", "bob@example.com", Some("123"))?;
-        let id = c.id.clone();
-        store.add(c);
+        let mut store = Store::open(&db)?.0;
+        let mut out = Vec::new();
+        let summary = run_batch(
+            input.as_bytes(),
+            &mut store,
+            &mut Printer::new(&mut out, ColorScheme::None),
+        )?;
+        assert_eq!((summary.succeeded, summary.failed), (3, 1));
+        assert!(summary.changed);
         store.save()?;
-        let store2 = Store::open(&db)?;
-        assert_eq!(store2.list().len(), 1);
-        assert_eq!(store2.list()[0].id, id);
+
+        let saved = Store::open(&db)?.0;
+        let names: Vec<&str> = saved.contacts().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Alice", "Bob Brown", "Carol"]);
         Ok(())
     }
 
     #[test]
-    fn atomic_write_permissions() -> Result<()> {
-        let dir = tempdir()?;
-        let db = dir.path().join("contacts.json");
-        let mut store = Store::open(&db)?;
-        store.add(Contact::new("C", "c@d.com", None)?);
-        store.save()?;
-        let meta = fs::metadata(&db)?;
-        #[cfg(unix)]
-        {
-            use std::os::unix::fs::PermissionsExt;
-            let mode = meta.permissions().mode();
-            assert_eq!(mode & 0o777, 0o600);
+    fn export_pages_splits_with_headers() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let contacts: Vec<Contact> = (0..2500)
+            .map(|i| Contact::new(&format!("User {}", i), "u@x.com", None))
+            .collect::<Result<_>>()?;
+        let refs: Vec<&Contact> = contacts.iter().collect();
+
+        let files = export_pages(&refs, ExportFormat::Csv, &[], None, dir.path(), 1000)?;
+        let names: Vec<_> = files.iter().map(|f| f.file_name().unwrap()).collect();
+        assert_eq!(
+            names,
+            ["contacts_001.csv", "contacts_002.csv", "contacts_003.csv"]
+        );
+        let rows: Vec<usize> = files
+            .iter()
+            .map(|f| Ok(std::fs::read_to_string(f)?.lines().count() - 1))
+            .collect::<Result<_>>()?;
+        assert_eq!(rows, [1000, 1000, 500]);
+
+        let files = export_pages(
+            &refs[..1],
+            ExportFormat::Csv,
+            &[],
+            None,
+            &dir.path().join("x"),
+            1000,
+        )?;
+        assert_eq!(files, [dir.path().join("x_001.csv")]);
+        Ok(())
+    }
+
+    #[test]
+    fn group_file_stem_is_a_plain_name() {
+        assert_eq!(group_file_stem("Acme Corp"), "Acme_Corp");
+        assert_eq!(group_file_stem("a/b\\c:d"), "a_b_c_d");
+        assert_eq!(group_file_stem(".."), "__");
+        assert_eq!(group_file_stem("v1.2"), "v1.2");
+    }
+
+    #[test]
+    fn color_decision_priority() {
+        // Explicit flags win over everything else.
+        assert!(color_decision(Some(true), true, true, false));
+        assert!(!color_decision(Some(false), false, false, true));
+        // Otherwise any disabling condition turns color off.
+        assert!(color_decision(None, false, false, true));
+        assert!(!color_decision(None, true, false, true));
+        assert!(!color_decision(None, false, true, true));
+        assert!(!color_decision(None, false, false, false));
+    }
+
+    #[test]
+    fn confirmation_defaults_to_no() -> Result<()> {
+        let answer = |input: &str| -> Result<(bool, String)> {
+            let mut out = Vec::new();
+            let yes = read_yes_no(
+                &mut Printer::new(&mut out, ColorScheme::None),
+                &mut input.as_bytes(),
+                "Remove Alice <alice@x.com>? [y/N]: ",
+            )?;
+            Ok((yes, String::from_utf8(out)?))
+        };
+        let (yes, prompt) = answer("y\n")?;
+        assert!(yes);
+        assert_eq!(prompt, "Remove Alice <alice@x.com>? [y/N]: ");
+        assert!(answer("YES\n")?.0);
+        assert!(!answer("\n")?.0);
+        assert!(!answer("no\n")?.0);
+        assert!(!answer("")?.0);
+        Ok(())
+    }
+
+    #[test]
+    fn batch_cannot_nest() -> Result<()> {
+        let mut store = Store::default();
+        let mut out = Vec::new();
+        let summary = run_batch(
+            "batch\nbogus-command\n".as_bytes(),
+            &mut store,
+            &mut Printer::new(&mut out, ColorScheme::None),
+        )?;
+        assert_eq!((summary.succeeded, summary.failed), (0, 2));
+        assert!(!summary.changed);
+        Ok(())
+    }
+
+    #[test]
+    fn read_import_file_sizes_progress_from_line_count() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let file = dir.path().join("big.csv");
+        let mut csv = String::from("name,email\n");
+        for i in 0..250 {
+            csv.push_str(&format!("P{i},p{i}@x.com\n"));
         }
+        csv.push_str(",missing@x.com");
+        std::fs::write(&file, csv)?;
+
+        let progress = ProgressBar::hidden();
+        let (valid, errors) = read_import_file(&file, ImportFormat::Csv, &[], &progress)?;
+        assert_eq!((valid.len(), errors.len()), (250, 1));
+        assert_eq!(errors[0].row, 251);
+        assert_eq!(progress.length(), Some(251));
+        assert_eq!(progress.position(), 251);
         Ok(())
     }
 
     #[test]
-    fn find_works() -> Result<()> {
-        let mut store = Store {
-            contacts: vec![],
-            path: PathBuf::from(""),
+    fn import_new_files_imports_and_moves() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let db = dir.path().join("contacts.json");
+        let inbox = dir.path().join("inbox");
+        std::fs::create_dir(&inbox)?;
+        let contacts = vec![Contact::new("Alice", "alice@x.com", None)?];
+        std::fs::write(inbox.join("new.json"), serde_json::to_vec(&contacts)?)?;
+        std::fs::write(inbox.join("notes.txt"), "ignored")?;
+
+        let options = StoreOptions::default();
+        let mut failed = HashMap::new();
+        let mut out = Vec::new();
+        let mut printer = Printer::new(&mut out, ColorScheme::None);
+        let mut poll = |failed: &mut HashMap<PathBuf, FileStamp>| {
+            import_new_files(
+                &db,
+                &options,
+                &inbox,
+                ImportFormat::Json,
+                failed,
+                &mut printer,
+            )
         };
-        store.add(Contact::new("Alice Smith", "alice@x.com", None)?);
-        store.add(Contact::new("Bob Brown", "bob@x.com", None)?);
-        let f = store.find("alice");
-        assert_eq!(f.len(), 1);
-        let f2 = store.find("@x.com");
-        assert_eq!(f2.len(), 2);
+        assert_eq!(poll(&mut failed)?, 1);
+        assert!(!inbox.join("new.json").exists());
+        assert!(inbox.join("processed/new.json").exists());
+        assert!(inbox.join("notes.txt").exists());
+        assert_eq!(Store::open(&db)?.0.contacts()[0].name, "Alice");
+
+        // Nothing new on the next poll.
+        assert_eq!(poll(&mut failed)?, 0);
+
+        // A half-written file fails, is not retried while unchanged, and
+        // is imported once complete.
+        let bob = vec![Contact::new("Bob", "bob@x.com", None)?];
+        let full = serde_json::to_vec(&bob)?;
+        std::fs::write(inbox.join("late.json"), &full[..full.len() / 2])?;
+        assert_eq!(poll(&mut failed)?, 0);
+        assert!(failed.contains_key(&inbox.join("late.json")));
+        assert_eq!(poll(&mut failed)?, 0);
+        std::fs::write(inbox.join("late.json"), &full)?;
+        assert_eq!(poll(&mut failed)?, 1);
+        assert!(failed.is_empty());
+
+        // The same name dropped again is imported and kept apart.
+        let carol = vec![Contact::new("Carol", "carol@x.com", None)?];
+        std::fs::write(inbox.join("new.json"), serde_json::to_vec(&carol)?)?;
+        assert_eq!(poll(&mut failed)?, 1);
+        assert!(inbox.join("processed/new.json").exists());
+        assert!(inbox.join("processed/new.1.json").exists());
+        assert_eq!(Store::open(&db)?.0.total_len(), 3);
         Ok(())
     }
 }
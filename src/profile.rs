@@ -0,0 +1,261 @@
+use anyhow::{anyhow, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::ffi::OsString;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::contact::IdFormat;
+use crate::store::Store;
+
+/// Data directory fixed at build time by setting `CONTACTS_DATA_DIR` for
+/// `cargo build`. When set it replaces `$XDG_DATA_HOME/contacts`.
+pub const BUILD_DATA_DIR: Option<&str> = option_env!("CONTACTS_DATA_DIR");
+
+/// Terminal palette for CLI output (`--color-scheme`).
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorScheme {
+    /// Bright colors for dark backgrounds.
+    #[default]
+    Dark,
+    /// Darker colors that stay readable on light backgrounds.
+    Light,
+    /// No colors at all, like `--no-color`.
+    None,
+}
+
+/// Named data files under `$XDG_DATA_HOME/contacts/profiles/<name>.json`,
+/// plus the default profile recorded in `$XDG_CONFIG_HOME/contacts/config.toml`.
+#[derive(Debug, Clone)]
+pub struct Profiles {
+    dir: PathBuf,
+    config: PathBuf,
+}
+
+/// The settings kept in `config.toml`.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Config {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    default_profile: Option<String>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    no_lock: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id_format: Option<IdFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    color_scheme: Option<ColorScheme>,
+}
+
+impl Profiles {
+    /// Locate the profile and config directories from the XDG variables,
+    /// falling back to `~/.local/share` and `~/.config`. [`BUILD_DATA_DIR`],
+    /// if set, is used for the data directory instead.
+    pub fn from_env() -> Result<Self> {
+        Self::from_vars(BUILD_DATA_DIR, |var| std::env::var_os(var))
+    }
+
+    fn from_vars(
+        build_data_dir: Option<&str>,
+        env: impl Fn(&str) -> Option<OsString>,
+    ) -> Result<Self> {
+        let xdg = |var: &str, fallback: &str| -> Result<PathBuf> {
+            match env(var).filter(|v| !v.is_empty()) {
+                Some(dir) => Ok(PathBuf::from(dir)),
+                None => env("HOME")
+                    .map(|home| Path::new(&home).join(fallback))
+                    .ok_or_else(|| anyhow!("neither {} nor HOME is set", var)),
+            }
+        };
+        let data_dir = match build_data_dir {
+            Some(dir) => PathBuf::from(dir),
+            None => xdg("XDG_DATA_HOME", ".local/share")?.join("contacts"),
+        };
+        Ok(Self::new(
+            data_dir,
+            xdg("XDG_CONFIG_HOME", ".config")?.join("contacts"),
+        ))
+    }
+
+    /// Profiles stored in `data_dir/profiles`, config in `config_dir/config.toml`.
+    pub fn new(data_dir: impl AsRef<Path>, config_dir: impl AsRef<Path>) -> Self {
+        Profiles {
+            dir: data_dir.as_ref().join("profiles"),
+            config: config_dir.as_ref().join("config.toml"),
+        }
+    }
+
+    /// Data file for profile `name`; the profile need not exist yet.
+    pub fn path(&self, name: &str) -> Result<PathBuf> {
+        validate_name(name)?;
+        Ok(self.dir.join(format!("{}.json", name)))
+    }
+
+    /// Create an empty data file for a new profile.
+    pub fn create(&self, name: &str) -> Result<PathBuf> {
+        let path = self.path(name)?;
+        if path.exists() {
+            return Err(anyhow!("profile '{}' already exists", name));
+        }
+        Store::new(path.clone()).save()?;
+        Ok(path)
+    }
+
+    /// Delete a profile's data file and its sidecars. Clears the default if
+    /// it pointed at this profile.
+    pub fn delete(&self, name: &str) -> Result<()> {
+        let path = self.path(name)?;
+        fs::remove_file(&path).with_context(|| format!("profile '{}' does not exist", name))?;
+        for ext in ["sha256", "undo", "audit.jsonl"] {
+            let mut sidecar = path.clone().into_os_string();
+            sidecar.push(".");
+            sidecar.push(ext);
+            // Missing sidecars are fine.
+            let _ = fs::remove_file(sidecar);
+        }
+        let mut config = self.read_config()?;
+        if config.default_profile.as_deref() == Some(name) {
+            config.default_profile = None;
+            self.write_config(&config)?;
+        }
+        Ok(())
+    }
+
+    /// Names of all existing profiles, sorted.
+    pub fn list(&self) -> Result<Vec<String>> {
+        let entries = match fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e).with_context(|| format!("reading {}", self.dir.display())),
+        };
+        let mut names = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            if path.extension().is_some_and(|e| e == "json") {
+                if let Some(stem) = path.file_stem() {
+                    names.push(stem.to_string_lossy().into_owned());
+                }
+            }
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    /// The profile used when neither `--profile` nor `--file` is given.
+    pub fn default_profile(&self) -> Result<Option<String>> {
+        Ok(self.read_config()?.default_profile)
+    }
+
+    /// Whether `no_lock = true` is set in the config file (see `--no-lock`).
+    pub fn no_lock(&self) -> Result<bool> {
+        Ok(self.read_config()?.no_lock)
+    }
+
+    /// The `id_format` set in the config file, if any (see `--id-format`).
+    pub fn id_format(&self) -> Result<Option<IdFormat>> {
+        Ok(self.read_config()?.id_format)
+    }
+
+    /// The `color_scheme` set in the config file, if any (see
+    /// `--color-scheme`).
+    pub fn color_scheme(&self) -> Result<Option<ColorScheme>> {
+        Ok(self.read_config()?.color_scheme)
+    }
+
+    /// Make an existing profile the default.
+    pub fn set_default(&self, name: &str) -> Result<()> {
+        if !self.path(name)?.exists() {
+            return Err(anyhow!("profile '{}' does not exist", name));
+        }
+        let mut config = self.read_config()?;
+        config.default_profile = Some(name.to_string());
+        self.write_config(&config)
+    }
+
+    fn read_config(&self) -> Result<Config> {
+        match fs::read_to_string(&self.config) {
+            Ok(text) => {
+                toml::from_str(&text).with_context(|| format!("parsing {}", self.config.display()))
+            }
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(e).with_context(|| format!("reading {}", self.config.display())),
+        }
+    }
+
+    fn write_config(&self, config: &Config) -> Result<()> {
+        if let Some(dir) = self.config.parent() {
+            fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))?;
+        }
+        fs::write(&self.config, toml::to_string(config)?)
+            .with_context(|| format!("writing {}", self.config.display()))
+    }
+}
+
+/// Profile names become file names, so keep them to a safe alphabet.
+fn validate_name(name: &str) -> Result<()> {
+    let ok = !name.is_empty()
+        && name.len() <= 64
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if ok {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "invalid profile name '{}' (use letters, digits, '-' and '_')",
+            name
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn create_list_default_delete() -> Result<()> {
+        let dir = tempdir()?;
+        let profiles = Profiles::new(dir.path().join("data"), dir.path().join("config"));
+        assert!(profiles.list()?.is_empty());
+        assert!(profiles.path("../evil").is_err());
+
+        profiles.create("work")?;
+        profiles.create("personal")?;
+        assert!(profiles.create("work").is_err());
+        assert_eq!(profiles.list()?, ["personal", "work"]);
+
+        assert!(profiles.set_default("missing").is_err());
+        profiles.set_default("work")?;
+        assert_eq!(profiles.default_profile()?.as_deref(), Some("work"));
+
+        profiles.delete("work")?;
+        assert_eq!(profiles.list()?, ["personal"]);
+        assert_eq!(profiles.default_profile()?, None);
+        Ok(())
+    }
+
+    #[test]
+    fn build_data_dir_overrides_xdg() -> Result<()> {
+        let env = |var: &str| match var {
+            "XDG_DATA_HOME" => Some(OsString::from("/xdg/data")),
+            "HOME" => Some(OsString::from("/home/u")),
+            _ => None,
+        };
+        let xdg = Profiles::from_vars(None, env)?;
+        assert_eq!(
+            xdg.path("work")?,
+            Path::new("/xdg/data/contacts/profiles/work.json")
+        );
+        assert_eq!(
+            xdg.config,
+            Path::new("/home/u/.config/contacts/config.toml")
+        );
+
+        let baked = Profiles::from_vars(Some("/srv/contacts"), env)?;
+        assert_eq!(
+            baked.path("work")?,
+            Path::new("/srv/contacts/profiles/work.json")
+        );
+        assert!(Profiles::from_vars(None, |_| None).is_err());
+        Ok(())
+    }
+}
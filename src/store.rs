@@ -0,0 +1,2594 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use fs2::FileExt;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cell::{Cell, RefCell};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use tempfile::NamedTempFile;
+
+use crate::audit::AuditEntry;
+use crate::contact::{validate_tag, Address, Contact, ContactUpdate, IdFormat};
+use crate::import::ContactField;
+
+/// On-disk format of the data file.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Format {
+    #[default]
+    Json,
+    /// A top-level `[[contacts]]` array; easier to edit by hand.
+    Toml,
+    /// Compact bincode encoding of the contact list.
+    #[cfg(feature = "bincode-backend")]
+    Binary,
+    /// MessagePack encoding of the contact list.
+    #[cfg(feature = "msgpack")]
+    Msgpack,
+}
+
+impl Format {
+    /// Guess the format from the file extension: `.toml` is TOML, anything
+    /// else JSON.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Format::Toml,
+            _ => Format::Json,
+        }
+    }
+
+    /// Like [`Format::from_path`], but looks at the first byte of non-TOML
+    /// files when a binary backend is enabled: a MessagePack array header
+    /// (`0x90`-`0x9f`, `0xdc`, `0xdd`) means MessagePack, and anything else
+    /// that can't start JSON means bincode.
+    #[cfg_attr(
+        not(any(feature = "bincode-backend", feature = "msgpack")),
+        allow(unused_variables)
+    )]
+    fn detect(path: &Path, data: &[u8]) -> Self {
+        let format = Self::from_path(path);
+        #[cfg(feature = "msgpack")]
+        if format == Format::Json && matches!(data.first(), Some(0x90..=0x9f | 0xdc | 0xdd)) {
+            return Format::Msgpack;
+        }
+        #[cfg(feature = "bincode-backend")]
+        if format == Format::Json {
+            if let Some(&first) = data.first() {
+                if !matches!(first, b'[' | b'{') && !first.is_ascii_whitespace() {
+                    return Format::Binary;
+                }
+            }
+        }
+        format
+    }
+}
+
+/// TOML has no top-level arrays, so the contact list sits under a key.
+#[derive(Serialize, Deserialize)]
+struct TomlFile {
+    #[serde(default)]
+    contacts: Vec<Contact>,
+}
+
+/// Something wrong with the data file that `open` worked around.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StoreWarning {
+    /// A contact whose id is not a valid [`IdFormat`] id. It is not loaded,
+    /// but every `save` writes it back unchanged.
+    InvalidId(String),
+}
+
+impl fmt::Display for StoreWarning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreWarning::InvalidId(id) => write!(
+                f,
+                "skipped contact with invalid id '{}'; it stays in the file untouched \
+                 until the id is fixed by hand",
+                id
+            ),
+        }
+    }
+}
+
+/// Orderings for [`Store::sorted_list`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum SortField {
+    /// Insertion order, as stored.
+    #[default]
+    Natural,
+    /// Name, ignoring case.
+    Name,
+    /// Creation time; contacts without one come first.
+    #[value(name = "created_at")]
+    CreatedAt,
+    /// Last modification time; contacts never updated come first.
+    #[value(name = "updated_at")]
+    UpdatedAt,
+}
+
+/// Contact counts from [`Store::counts`]. Displays as the total, followed by
+/// the archived count when there are any: `5 (2 archived)`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Counts {
+    pub active: usize,
+    pub archived: usize,
+    pub total: usize,
+}
+
+impl<'a> FromIterator<&'a Contact> for Counts {
+    fn from_iter<I: IntoIterator<Item = &'a Contact>>(iter: I) -> Self {
+        let mut counts = Counts::default();
+        for c in iter {
+            if c.archived {
+                counts.archived += 1;
+            } else {
+                counts.active += 1;
+            }
+            counts.total += 1;
+        }
+        counts
+    }
+}
+
+impl fmt::Display for Counts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.total)?;
+        if self.archived > 0 {
+            write!(f, " ({} archived)", self.archived)?;
+        }
+        Ok(())
+    }
+}
+
+/// Default for [`StoreOptions::max_file_size`]: 50 MB.
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Default for [`StoreOptions::tombstone_ttl_days`].
+pub const DEFAULT_TOMBSTONE_TTL_DAYS: u32 = 90;
+
+/// How [`Store::open_with`] reads the data file.
+#[derive(Debug, Clone)]
+pub struct StoreOptions {
+    /// Data file format; `None` picks it from the extension.
+    pub format: Option<Format>,
+    /// Don't compare the file against its `.sha256` sidecar.
+    pub skip_verify: bool,
+    /// Refuse to read data files larger than this many bytes, so a corrupt
+    /// or hostile file can't exhaust memory. `None` means no limit.
+    pub max_file_size: Option<u64>,
+    /// Drop contacts archived more than this many days ago while opening.
+    /// `None` keeps archived contacts forever.
+    pub tombstone_ttl_days: Option<u32>,
+    /// Don't lock the data file when reading or saving, for filesystems
+    /// that don't support locks (some NFS and FUSE mounts). Concurrent
+    /// writers can then corrupt the file.
+    pub no_lock: bool,
+    /// Fail instead of starting an empty store when the file is missing.
+    pub must_exist: bool,
+    /// Id format for contacts created through this store (see
+    /// [`Store::id_format`]).
+    pub id_format: IdFormat,
+}
+
+impl Default for StoreOptions {
+    fn default() -> Self {
+        StoreOptions {
+            format: None,
+            skip_verify: false,
+            max_file_size: Some(DEFAULT_MAX_FILE_SIZE),
+            tombstone_ttl_days: Some(DEFAULT_TOMBSTONE_TTL_DAYS),
+            no_lock: false,
+            must_exist: false,
+            id_format: IdFormat::Uuid,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Store {
+    pub(crate) contacts: Vec<Contact>,
+    pub(crate) path: PathBuf,
+    format: Format,
+    /// Contact id -> position in `contacts`. Rebuilt whenever positions shift.
+    index: HashMap<String, usize>,
+    /// Lowercased primary email -> position of the first contact using it.
+    email_index: HashMap<String, usize>,
+    /// Lowercased, trimmed company -> positions of its contacts, in order.
+    company_index: HashMap<String, Vec<usize>>,
+    /// Lowercased primary email domain -> positions of its contacts, in order.
+    domain_index: HashMap<String, Vec<usize>>,
+    /// Contacts `open_with` left out for an invalid id, saved back as read.
+    invalid_id_contacts: Vec<Contact>,
+    /// Expired archived contacts dropped by `open_with`.
+    auto_purged: usize,
+    /// Skip file locks (see [`StoreOptions::no_lock`]).
+    no_lock: bool,
+    id_format: IdFormat,
+    /// Save JSON without whitespace (see [`Store::compact_json`]).
+    compact_json: bool,
+    /// Contacts changed since they were loaded or last saved. A `Cell` so
+    /// that `save(&self)` can clear it.
+    dirty: Cell<bool>,
+    /// Audit log entries waiting for the next `save`.
+    pub(crate) pending_audit: RefCell<Vec<AuditEntry>>,
+    // We keep the file handle locked during operations that require a lock.
+    // The handle is not stored persistently; locking operations open/lock/close on demand.
+}
+
+impl Store {
+    /// Open a data file with default [`StoreOptions`]: the format comes
+    /// from the extension and the checksum is verified if present. Problems
+    /// the load worked around are returned next to the store.
+    pub fn open(path: impl AsRef<Path>) -> Result<(Self, Vec<StoreWarning>)> {
+        Self::open_with(path, &StoreOptions::default())
+    }
+
+    /// Open the data file if it exists, otherwise start an empty store; the
+    /// file is then created by the first `save`. Same as [`Store::open`].
+    pub fn open_or_create(path: &Path) -> Result<(Self, Vec<StoreWarning>)> {
+        Self::open(path)
+    }
+
+    /// Open a data file that must already exist; a missing file is an error
+    /// rather than an empty store.
+    pub fn open_existing(path: &Path) -> Result<(Self, Vec<StoreWarning>)> {
+        let options = StoreOptions {
+            must_exist: true,
+            ..Default::default()
+        };
+        Self::open_with(path, &options)
+    }
+
+    /// Open a data file; `save` keeps using the format it was opened with.
+    pub fn open_with(
+        path: impl AsRef<Path>,
+        options: &StoreOptions,
+    ) -> Result<(Self, Vec<StoreWarning>)> {
+        let path = path.as_ref().to_path_buf();
+        let mut format = options.format.unwrap_or_else(|| Format::from_path(&path));
+        let contacts = if path.exists() {
+            let file = OpenOptions::new()
+                .read(true)
+                .open(&path)
+                .with_context(|| format!("opening data file: {}", path.display()))?;
+            // Lock for reading to prevent simultaneous writes while reading
+            if !options.no_lock {
+                lock_file(&file, false).with_context(|| "acquiring shared lock for read")?;
+            }
+
+            // Check the size before reading anything into memory.
+            let size = file
+                .metadata()
+                .with_context(|| "reading data file metadata")?
+                .len();
+            if let Some(max) = options.max_file_size.filter(|&max| size > max) {
+                return Err(anyhow!(
+                    "data file {} is {} bytes, over the {}-byte limit (see --max-file-size)",
+                    path.display(),
+                    size,
+                    max
+                ));
+            }
+
+            let mut buf = Vec::new();
+            // Read while locked
+            let mut reader = file;
+            reader
+                .read_to_end(&mut buf)
+                .with_context(|| "reading data file")?;
+            if !options.skip_verify && sidecar_path(&path, "sha256").exists() {
+                check_digest(&path, &buf)?;
+            }
+            if options.format.is_none() {
+                format = Format::detect(&path, &buf);
+            }
+            // `save` creates the file empty before renaming the real data
+            // over it, so a concurrent reader may briefly see zero bytes.
+            if buf.iter().all(u8::is_ascii_whitespace) {
+                Vec::new()
+            } else {
+                parse_contacts_as(&buf, format)?
+            }
+        } else if options.must_exist {
+            return Err(anyhow!("data file {} does not exist", path.display()));
+        } else {
+            Vec::new()
+        };
+
+        // Hand-edited files may carry made-up ids; set those contacts aside
+        // and report them rather than failing the whole load. `save` writes
+        // them back, so they are never lost.
+        let (contacts, invalid_id_contacts): (Vec<_>, Vec<_>) = contacts
+            .into_iter()
+            .partition(|c| IdFormat::is_valid_id(&c.id));
+        let warnings = invalid_id_contacts
+            .iter()
+            .map(|c| StoreWarning::InvalidId(c.id.clone()))
+            .collect();
+
+        let mut store = Store {
+            contacts,
+            path,
+            format,
+            invalid_id_contacts,
+            no_lock: options.no_lock,
+            id_format: options.id_format,
+            ..Default::default()
+        };
+        if let Some(days) = options.tombstone_ttl_days {
+            let cutoff = Utc::now() - Duration::days(days.into());
+            let before = store.contacts.len();
+            store
+                .contacts
+                .retain(|c| !c.archived || c.archived_at.is_none_or(|t| t > cutoff));
+            store.auto_purged = before - store.contacts.len();
+            // The next save writes the purge back to disk.
+            store.dirty.set(store.auto_purged > 0);
+        }
+        store.rebuild_indices();
+        Ok((store, warnings))
+    }
+
+    /// The id format new contacts for this store should use, from
+    /// [`StoreOptions::id_format`].
+    pub fn id_format(&self) -> IdFormat {
+        self.id_format
+    }
+
+    /// Error listing every id used by more than one contact. Only a concern
+    /// with [`IdFormat::Short`] or [`IdFormat::Hash`] ids, or hand-edited files.
+    pub fn check_id_uniqueness(&self) -> Result<()> {
+        let mut seen = std::collections::HashSet::new();
+        let mut duplicates: Vec<&str> = self
+            .contacts
+            .iter()
+            .map(|c| c.id.as_str())
+            .filter(|id| !seen.insert(*id))
+            .collect();
+        if duplicates.is_empty() {
+            return Ok(());
+        }
+        duplicates.sort_unstable();
+        duplicates.dedup();
+        Err(anyhow!("duplicate contact ids: {}", duplicates.join(", ")))
+    }
+
+    /// Number of expired archived contacts dropped by `open`
+    /// (see [`StoreOptions::tombstone_ttl_days`]).
+    pub fn auto_purged(&self) -> usize {
+        self.auto_purged
+    }
+
+    /// Compare the data file against the SHA-256 recorded in its `.sha256`
+    /// sidecar (written by `save`). Errors if either file is missing or the
+    /// digests differ, unless the data is exactly the `.undo` copy: then a
+    /// save was interrupted before replacing it, and the data is intact.
+    pub fn verify_checksum(&self) -> Result<()> {
+        let data = fs::read(&self.path)
+            .with_context(|| format!("reading data file: {}", self.path.display()))?;
+        check_digest(&self.path, &data)
+    }
+
+    /// An empty in-memory store that will `save` to `path`, in the format
+    /// its extension implies. Nothing is read from disk.
+    pub fn new(path: PathBuf) -> Self {
+        let format = Format::from_path(&path);
+        Self::empty(path, format)
+    }
+
+    /// A copy of the store, to be saved at `path`, with personal data
+    /// replaced: contact `n` (from 1) becomes "Contact_n" with the address
+    /// contact_n@example.com, any phone becomes +10000000000, and postal
+    /// addresses and social handles are removed. Ids, tags and dates are
+    /// kept so the copy still reproduces bugs.
+    pub fn anonymized(&self, path: impl AsRef<Path>) -> Store {
+        let path = path.as_ref();
+        let mut copy = Store::empty(path, Format::from_path(path));
+        for (i, c) in self.contacts.iter().enumerate() {
+            let n = i + 1;
+            let mut c = c.clone();
+            c.name = format!("Contact_{}", n);
+            c.emails = vec![format!("contact_{}@example.com", n)];
+            if c.phone.is_some() {
+                c.phone = Some("+10000000000".into());
+            }
+            c.address = None;
+            c.social_handles.clear();
+            copy.add(c);
+        }
+        copy
+    }
+
+    /// Rebind the store so `save` writes to `path` instead, e.g. to load one
+    /// file and save a merged copy elsewhere. The format is unchanged.
+    pub fn with_path(self, path: impl AsRef<Path>) -> Store {
+        Store {
+            path: path.as_ref().to_path_buf(),
+            dirty: Cell::new(true),
+            ..self
+        }
+    }
+
+    /// An empty store that will `save` to `path` in `format`, ignoring
+    /// whatever the file currently holds.
+    pub fn empty(path: impl AsRef<Path>, format: Format) -> Self {
+        Store {
+            path: path.as_ref().to_path_buf(),
+            format,
+            dirty: Cell::new(true),
+            ..Default::default()
+        }
+    }
+
+    /// Load contacts from a reader (e.g. stdin) instead of a file. No lock is
+    /// taken and the store has no path, so use [`Store::write_json`] rather
+    /// than [`Store::save`]. Empty input is an empty store.
+    pub fn from_reader(mut reader: impl Read) -> Result<Self> {
+        let mut buf = String::new();
+        reader
+            .read_to_string(&mut buf)
+            .with_context(|| "reading contacts from input")?;
+        let contacts = if buf.trim().is_empty() {
+            Vec::new()
+        } else {
+            parse_contacts(&buf)?
+        };
+        let mut store = Store::default();
+        store.replace_contacts(contacts);
+        store.dirty.set(false);
+        Ok(store)
+    }
+
+    /// Write the contacts as JSON, in the same layout `save` uses.
+    pub fn write_json(&self, mut w: impl Write) -> Result<()> {
+        w.write_all(&self.json_bytes()?)
+            .with_context(|| "writing JSON output")?;
+        w.flush().with_context(|| "flushing JSON output")?;
+        Ok(())
+    }
+
+    /// Pretty JSON, or compact after [`Store::compact_json`].
+    fn json_bytes(&self) -> Result<Vec<u8>> {
+        let contacts = self.to_save();
+        if self.compact_json {
+            serde_json::to_vec(&contacts)
+        } else {
+            serde_json::to_vec_pretty(&contacts)
+        }
+        .with_context(|| "serializing contacts to JSON")
+    }
+
+    /// Everything `save` writes: the loaded contacts, then any set aside by
+    /// `open_with` for an invalid id.
+    fn to_save(&self) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .chain(&self.invalid_id_contacts)
+            .collect()
+    }
+
+    /// Swap in a whole new contact list, keeping the index in sync.
+    pub(crate) fn replace_contacts(&mut self, contacts: Vec<Contact>) {
+        self.contacts = contacts;
+        self.rebuild_indices();
+        self.dirty.set(true);
+    }
+
+    /// Replace the contact stored under `id` with `c` (which should carry the
+    /// same id). Returns `false` if there is no such contact.
+    pub(crate) fn replace_contact(&mut self, id: &str, c: Contact) -> bool {
+        let Some(&i) = self.index.get(id) else {
+            return false;
+        };
+        self.contacts[i] = c;
+        self.rebuild_indices();
+        self.dirty.set(true);
+        true
+    }
+
+    fn rebuild_indices(&mut self) {
+        self.index.clear();
+        self.email_index.clear();
+        self.company_index.clear();
+        self.domain_index.clear();
+        for (i, c) in self.contacts.iter().enumerate() {
+            self.index.insert(c.id.clone(), i);
+            self.email_index
+                .entry(email_key(c.primary_email()))
+                .or_insert(i);
+            if let Some(company) = &c.company {
+                self.company_index
+                    .entry(company_key(company))
+                    .or_default()
+                    .push(i);
+            }
+            if let Some(domain) = email_domain(c.primary_email()) {
+                self.domain_index.entry(domain).or_default().push(i);
+            }
+        }
+    }
+
+    /// True if there are changes that `save` would write.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty.get()
+    }
+
+    /// All contacts, archived ones included, in insertion order.
+    pub fn contacts(&self) -> &[Contact] {
+        &self.contacts
+    }
+
+    /// Old name for [`Store::contacts`].
+    #[deprecated(note = "use `Store::contacts`")]
+    pub fn list(&self) -> &[Contact] {
+        self.contacts()
+    }
+
+    /// Direct access to the contact list. The id and email lookups are not
+    /// updated automatically: after changing an id or primary email, or
+    /// adding, removing or reordering contacts, call [`Store::reindex`].
+    /// The store is assumed modified.
+    pub fn contacts_mut(&mut self) -> &mut Vec<Contact> {
+        self.dirty.set(true);
+        &mut self.contacts
+    }
+
+    /// Rebuild the id and email lookups after edits through
+    /// [`Store::contacts_mut`].
+    pub fn reindex(&mut self) {
+        self.rebuild_indices();
+    }
+
+    /// Consume the store and return its contacts, archived ones included,
+    /// without cloning. Unsaved changes are not written.
+    pub fn into_contacts(self) -> Vec<Contact> {
+        self.contacts
+    }
+
+    /// Same as [`Store::into_contacts`].
+    ///
+    /// ```compile_fail
+    /// let store = secure_contacts::Store::default();
+    /// let contacts = store.take();
+    /// store.len(); // error: `store` was moved by `take`
+    /// ```
+    pub fn take(self) -> Vec<Contact> {
+        self.into_contacts()
+    }
+
+    /// The first `n` contacts in storage order, archived ones included
+    /// (fewer if the store is smaller).
+    pub fn head(&self, n: usize) -> &[Contact] {
+        &self.contacts[..n.min(self.contacts.len())]
+    }
+
+    /// Up to `n` distinct active contacts chosen uniformly at random, in
+    /// random order. Asking for more than there are returns all of them.
+    pub fn sample(&self, n: usize, rng: &mut impl Rng) -> Vec<&Contact> {
+        let active: Vec<&Contact> = self.contacts.iter().filter(|c| !c.archived).collect();
+        active.choose_multiple(rng, n).copied().collect()
+    }
+
+    /// The last `n` contacts in storage order, archived ones included
+    /// (fewer if the store is smaller).
+    pub fn tail(&self, n: usize) -> &[Contact] {
+        &self.contacts[self.contacts.len().saturating_sub(n)..]
+    }
+
+    /// All contacts, archived ones included, ordered by `by` (ties keep
+    /// insertion order), or in the opposite order if `reverse` is set.
+    pub fn sorted_list(&self, by: SortField, reverse: bool) -> Vec<&Contact> {
+        let mut sorted: Vec<&Contact> = self.contacts.iter().collect();
+        match by {
+            SortField::Natural => {}
+            SortField::Name => sorted.sort_by_cached_key(|c| c.name.to_lowercase()),
+            SortField::CreatedAt => sorted.sort_by_key(|c| c.created_at),
+            SortField::UpdatedAt => sorted.sort_by_key(|c| c.updated_at),
+        }
+        if reverse {
+            sorted.reverse();
+        }
+        sorted
+    }
+
+    /// Up to `limit` active contacts, ordered by id, that come after
+    /// `after_id` (from the start if `None`), plus the cursor for the next
+    /// page, which is `None` once the end is reached. Ordering by id keeps
+    /// pages stable when contacts are added or removed in between, and a
+    /// cursor whose contact was deleted still works.
+    pub fn paginate_with_cursor(
+        &self,
+        after_id: Option<&str>,
+        limit: usize,
+    ) -> (Vec<&Contact>, Option<String>) {
+        let mut active: Vec<&Contact> = self.contacts.iter().filter(|c| !c.archived).collect();
+        active.sort_by(|a, b| a.id.cmp(&b.id));
+        let start = after_id.map_or(0, |after| {
+            active.partition_point(|c| c.id.as_str() <= after)
+        });
+        let rest = &active[start..];
+        let page = rest[..limit.min(rest.len())].to_vec();
+        let next = match page.last() {
+            Some(last) if rest.len() > page.len() => Some(last.id.clone()),
+            _ => None,
+        };
+        (page, next)
+    }
+
+    /// The contact at `index` in [`Store::contacts`], like `slice::get`.
+    pub fn get(&self, index: usize) -> Option<&Contact> {
+        self.contacts.get(index)
+    }
+
+    /// Active, archived and total contact counts in one pass.
+    pub fn counts(&self) -> Counts {
+        self.contacts.iter().collect()
+    }
+
+    /// How often each word appears in `field` across all contacts, archived
+    /// ones included. Words are split on whitespace, lowercased and stripped
+    /// of surrounding punctuation (so "Corp." counts as "corp"); common
+    /// English stop words are left out.
+    pub fn word_frequency(&self, field: ContactField) -> BTreeMap<String, usize> {
+        let mut counts = BTreeMap::new();
+        for c in &self.contacts {
+            for text in field_text(c, field) {
+                for word in text.split_whitespace() {
+                    let word = word
+                        .trim_matches(|ch: char| !ch.is_alphanumeric())
+                        .to_lowercase();
+                    if !word.is_empty() && !STOP_WORDS.contains(&word.as_str()) {
+                        *counts.entry(word).or_insert(0) += 1;
+                    }
+                }
+            }
+        }
+        counts
+    }
+
+    /// Number of active (non-archived) contacts.
+    pub fn len(&self) -> usize {
+        self.total_len() - self.archived_len()
+    }
+
+    /// True if there are no active contacts (archived ones may remain).
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Number of contacts including archived ones.
+    pub fn total_len(&self) -> usize {
+        self.contacts.len()
+    }
+
+    pub fn archived_len(&self) -> usize {
+        self.contacts.iter().filter(|c| c.archived).count()
+    }
+
+    pub fn add(&mut self, c: Contact) {
+        let i = self.contacts.len();
+        self.index.insert(c.id.clone(), i);
+        self.email_index
+            .entry(email_key(c.primary_email()))
+            .or_insert(i);
+        if let Some(company) = &c.company {
+            self.company_index
+                .entry(company_key(company))
+                .or_default()
+                .push(i);
+        }
+        if let Some(domain) = email_domain(c.primary_email()) {
+            self.domain_index.entry(domain).or_default().push(i);
+        }
+        self.contacts.push(c);
+        self.dirty.set(true);
+    }
+
+    pub fn remove(&mut self, id: &str) -> bool {
+        match self.index.get(id) {
+            Some(&i) => {
+                self.contacts.remove(i);
+                self.rebuild_indices();
+                self.dirty.set(true);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// O(1) lookup by id.
+    pub fn find_by_id(&self, id: &str) -> Option<&Contact> {
+        self.index.get(id).map(|&i| &self.contacts[i])
+    }
+
+    /// Look up several ids at once. Contacts come back in the order of `ids`;
+    /// unknown ids are skipped.
+    pub fn find_by_ids(&self, ids: &[&str]) -> Vec<&Contact> {
+        ids.iter().filter_map(|id| self.find_by_id(id)).collect()
+    }
+
+    /// Contacts, archived ones included, whose id starts with `prefix`
+    /// (ignoring case). An empty prefix matches nothing.
+    pub fn find_by_id_prefix(&self, prefix: &str) -> Vec<&Contact> {
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+        let prefix = prefix.to_lowercase();
+        self.contacts
+            .iter()
+            .filter(|c| c.id.to_lowercase().starts_with(&prefix))
+            .collect()
+    }
+
+    /// Exact, case-insensitive primary email lookup. If several contacts share the
+    /// address, the first one is returned.
+    pub fn find_by_email(&self, email: &str) -> Option<&Contact> {
+        self.email_index
+            .get(&email_key(email))
+            .map(|&i| &self.contacts[i])
+    }
+
+    /// Exact, case-insensitive company lookup (surrounding whitespace is
+    /// ignored), archived contacts included, in store order.
+    pub fn find_by_company_exact(&self, company: &str) -> Vec<&Contact> {
+        self.company_index
+            .get(&company_key(company))
+            .map_or_else(Vec::new, |positions| {
+                positions.iter().map(|&i| &self.contacts[i]).collect()
+            })
+    }
+
+    /// Contacts whose primary email is at `domain` (ignoring case and a
+    /// leading `@`), archived ones included, in store order.
+    pub fn find_by_email_domain(&self, domain: &str) -> Vec<&Contact> {
+        self.domain_index
+            .get(&domain_key(domain))
+            .map_or_else(Vec::new, |positions| {
+                positions.iter().map(|&i| &self.contacts[i]).collect()
+            })
+    }
+
+    /// Mutable lookup by id. Callers must not change the contact's `id`,
+    /// primary email or company; use [`Store::update_contact`] for that. The store is
+    /// assumed modified whenever a contact is returned.
+    pub fn find_by_id_mut(&mut self, id: &str) -> Option<&mut Contact> {
+        let i = *self.index.get(id)?;
+        self.dirty.set(true);
+        Some(&mut self.contacts[i])
+    }
+
+    /// Apply `update` to the contact with `id`. Returns `Ok(false)` if there is
+    /// no such contact; validation errors leave the contact unchanged.
+    pub fn update_contact(&mut self, id: &str, update: ContactUpdate) -> Result<bool> {
+        let Some(c) = self.find_by_id_mut(id) else {
+            return Ok(false);
+        };
+        let old_email = c.primary_email().to_string();
+        let old_company = c.company.clone();
+        c.apply(update)?;
+        if c.primary_email() != old_email || c.company != old_company {
+            self.rebuild_indices();
+        }
+        Ok(true)
+    }
+
+    /// Mark the contact archived (or not). Returns `false` if there is no
+    /// such contact. Re-archiving keeps the original `archived_at`.
+    pub fn set_archived(&mut self, id: &str, archived: bool) -> bool {
+        match self.find_by_id_mut(id) {
+            Some(c) => {
+                c.archived = archived;
+                c.archived_at = if archived {
+                    Some(c.archived_at.unwrap_or_else(Utc::now))
+                } else {
+                    None
+                };
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Restore the contact `id` from the `.undo` sidecar, which holds the data
+    /// file as it was before the last save. Only that contact is re-added, so
+    /// other changes since then are kept.
+    pub fn unremove(&mut self, id: &str) -> Result<()> {
+        if self.find_by_id(id).is_some() {
+            return Err(anyhow!("contact {} is not removed", id));
+        }
+        let undo_path = sidecar_path(&self.path, "undo");
+        let data = fs::read(&undo_path)
+            .with_context(|| format!("reading undo file {}", undo_path.display()))?;
+        let contact = parse_contacts_as(&data, Format::detect(&self.path, &data))?
+            .into_iter()
+            .find(|c| c.id == id)
+            .ok_or_else(|| anyhow!("No contact with id {} in the undo file", id))?;
+        self.add(contact);
+        Ok(())
+    }
+
+    /// Re-run [`Contact::validate`] on every contact, archived ones included,
+    /// for data saved under older, looser rules. Returns each failing
+    /// contact's id with its first error, in store order.
+    pub fn validate_all(&self) -> Vec<(String, anyhow::Error)> {
+        self.contacts
+            .iter()
+            .filter_map(|c| c.validate().err().map(|e| (c.id.clone(), e)))
+            .collect()
+    }
+
+    /// Permanently drop every archived contact. Returns how many were removed.
+    pub fn purge_archived(&mut self) -> usize {
+        let before = self.contacts.len();
+        self.contacts.retain(|c| !c.archived);
+        self.rebuild_indices();
+        let removed = before - self.contacts.len();
+        if removed > 0 {
+            self.dirty.set(true);
+        }
+        removed
+    }
+
+    /// Case-insensitive substring match on name or primary email, and also the
+    /// phone number when `search_phone` is set. Archived contacts are skipped
+    /// unless `include_archived` is set.
+    pub fn find(&self, q: &str, include_archived: bool, search_phone: bool) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| include_archived || !c.archived)
+            .filter(|c| matches_query(c, q, search_phone))
+            .collect()
+    }
+
+    /// The first active contact, in store order, that [`Store::find`] would
+    /// return for `q`. Stops scanning at the match.
+    pub fn find_first(&self, q: &str) -> Option<&Contact> {
+        self.contacts
+            .iter()
+            .find(|c| !c.archived && matches_query(c, q, false))
+    }
+
+    /// Active contacts that [`Store::find`] would not return for `q`: neither
+    /// the name nor the primary email contains it.
+    pub fn find_not(&self, q: &str) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| !c.archived)
+            .filter(|c| !matches_query(c, q, false))
+            .collect()
+    }
+
+    /// All contacts, archived ones included, split into those `pred` accepts
+    /// and those it rejects, in one pass. Both keep store order.
+    pub fn split_by_predicate<F: Fn(&Contact) -> bool>(
+        &self,
+        pred: F,
+    ) -> (Vec<&Contact>, Vec<&Contact>) {
+        self.contacts.iter().partition(|c| pred(c))
+    }
+
+    /// Contacts carrying every tag in `tags`, archived ones included.
+    pub fn find_contains_all_tags(&self, tags: &[&str]) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| c.has_all_tags(tags))
+            .collect()
+    }
+
+    /// Contacts carrying at least one tag in `tags`, archived ones included.
+    pub fn find_contains_any_tag(&self, tags: &[&str]) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| c.has_any_tag(tags))
+            .collect()
+    }
+
+    /// Contacts whose handle on `platform` equals `handle`, both compared
+    /// case-insensitively.
+    pub fn find_by_social(&self, platform: &str, handle: &str) -> Vec<&Contact> {
+        let platform = platform.trim().to_lowercase();
+        self.contacts
+            .iter()
+            .filter(|c| {
+                c.social_handles
+                    .get(&platform)
+                    .is_some_and(|h| h.eq_ignore_ascii_case(handle.trim()))
+            })
+            .collect()
+    }
+
+    /// Active contacts with no phone number (missing or blank).
+    pub fn find_without_phone(&self) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| !c.archived)
+            .filter(|c| c.phone.as_deref().is_none_or(|p| p.trim().is_empty()))
+            .collect()
+    }
+
+    /// Active contacts with no email address. `Contact::new` always sets one,
+    /// but hand-edited or imported files may not.
+    pub fn find_without_email(&self) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| !c.archived)
+            .filter(|c| c.emails.iter().all(|e| e.trim().is_empty()))
+            .collect()
+    }
+
+    /// Contacts created at or after `since` and strictly before `before`.
+    /// With neither bound set, every contact is returned.
+    pub fn find_in_date_range(
+        &self,
+        since: Option<DateTime<Utc>>,
+        before: Option<DateTime<Utc>>,
+    ) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| c.created_between(since, before))
+            .collect()
+    }
+
+    /// Contacts modified strictly after `since`, archived ones included.
+    /// Contacts never updated count as modified when they were created;
+    /// those with neither timestamp are left out.
+    pub fn find_since(&self, since: DateTime<Utc>) -> Vec<&Contact> {
+        self.contacts
+            .iter()
+            .filter(|c| c.updated_at.or(c.created_at).is_some_and(|t| t > since))
+            .collect()
+    }
+
+    /// Call `updater` on every contact, archived ones included, then rebuild
+    /// the lookups once. Contacts it changed get a new `updated_at`. Returns
+    /// the number of contacts modified.
+    pub fn update_all<F: FnMut(&mut Contact)>(&mut self, mut updater: F) -> usize {
+        let now = Utc::now();
+        let mut modified = 0;
+        for c in &mut self.contacts {
+            let before = c.clone();
+            updater(c);
+            if *c != before {
+                c.updated_at = Some(now);
+                modified += 1;
+            }
+        }
+        if modified > 0 {
+            self.dirty.set(true);
+            self.rebuild_indices();
+        }
+        modified
+    }
+
+    /// Rename tag `from` to `to` on every contact, dropping the duplicate if a
+    /// contact already carries `to`. `to` is checked like any new tag first.
+    /// Returns the number of contacts modified.
+    pub fn rename_tag(&mut self, from: &str, to: &str) -> Result<usize> {
+        let to = validate_tag(to)?;
+        if to == from {
+            return Ok(0);
+        }
+        let now = Utc::now();
+        let mut modified = 0;
+        for c in &mut self.contacts {
+            if !c.tags.iter().any(|t| t == from) {
+                continue;
+            }
+            let mut renamed = Vec::with_capacity(c.tags.len());
+            for t in c.tags.drain(..) {
+                let t = if t == from { to.clone() } else { t };
+                if !renamed.contains(&t) {
+                    renamed.push(t);
+                }
+            }
+            c.tags = renamed;
+            c.updated_at = Some(now);
+            modified += 1;
+        }
+        if modified > 0 {
+            self.dirty.set(true);
+        }
+        Ok(modified)
+    }
+
+    /// Add `tag` to each contact in `ids` that lacks it. Unknown ids are
+    /// skipped. Returns the number of contacts modified.
+    pub fn bulk_tag(&mut self, ids: &[&str], tag: &str) -> Result<usize> {
+        let tag = validate_tag(tag)?;
+        let now = Utc::now();
+        let mut modified = 0;
+        for id in ids {
+            let Some(&i) = self.index.get(*id) else {
+                continue;
+            };
+            let c = &mut self.contacts[i];
+            if !c.tags.contains(&tag) {
+                c.tags.push(tag.clone());
+                c.updated_at = Some(now);
+                modified += 1;
+            }
+        }
+        if modified > 0 {
+            self.dirty.set(true);
+        }
+        Ok(modified)
+    }
+
+    /// Remove `tag` from every contact. Returns the number of contacts modified.
+    pub fn delete_tag(&mut self, tag: &str) -> usize {
+        let now = Utc::now();
+        let mut modified = 0;
+        for c in &mut self.contacts {
+            let before = c.tags.len();
+            c.tags.retain(|t| t != tag);
+            if c.tags.len() != before {
+                c.updated_at = Some(now);
+                modified += 1;
+            }
+        }
+        if modified > 0 {
+            self.dirty.set(true);
+        }
+        modified
+    }
+
+    /// Persist data atomically and securely. Does nothing if no contact
+    /// changed since the store was opened or last saved.
+    pub fn save(&self) -> Result<()> {
+        if !self.is_dirty() {
+            return Ok(());
+        }
+        self.save_as(&self.path, self.format)?;
+        self.dirty.set(false);
+        self.flush_audit()
+    }
+
+    /// Run `f` against the store and save if it succeeds. If `f` fails, the
+    /// contacts are put back exactly as they were and its error is returned.
+    pub fn transaction<F: FnOnce(&mut Store) -> Result<()>>(&mut self, f: F) -> Result<()> {
+        let snapshot = self.contacts.clone();
+        let was_dirty = self.is_dirty();
+        let audit_len = self.pending_audit.get_mut().len();
+        if let Err(e) = f(self) {
+            self.contacts = snapshot;
+            self.rebuild_indices();
+            self.dirty.set(was_dirty);
+            self.pending_audit.get_mut().truncate(audit_len);
+            return Err(e);
+        }
+        self.save()
+    }
+
+    /// Write the contacts to `path` in the compact binary format. The store
+    /// itself keeps its own path and format.
+    #[cfg(feature = "bincode-backend")]
+    pub fn save_binary(&self, path: &Path) -> Result<()> {
+        self.save_as(path, Format::Binary)
+    }
+
+    /// Open a file written by [`Store::save_binary`]; later `save` calls
+    /// keep the binary format.
+    #[cfg(feature = "bincode-backend")]
+    pub fn open_binary(path: &Path) -> Result<(Store, Vec<StoreWarning>)> {
+        let options = StoreOptions {
+            format: Some(Format::Binary),
+            ..Default::default()
+        };
+        Self::open_with(path, &options)
+    }
+
+    fn save_as(&self, path: &Path, format: Format) -> Result<()> {
+        // Serialize contacts (JSON, TOML or binary).
+        let data = match format {
+            Format::Json => self.json_bytes()?,
+            Format::Toml => toml::to_string_pretty(&TomlFile {
+                contacts: self.to_save().into_iter().cloned().collect(),
+            })
+            .with_context(|| "serializing contacts to TOML")?
+            .into_bytes(),
+            #[cfg(feature = "bincode-backend")]
+            Format::Binary => bincode::serialize(&self.to_save())
+                .with_context(|| "serializing contacts to binary")?,
+            #[cfg(feature = "msgpack")]
+            Format::Msgpack => rmp_serde::to_vec(&self.to_save())
+                .with_context(|| "serializing contacts to MessagePack")?,
+        };
+        self.write_atomic(path, &data)
+    }
+
+    /// Write JSON without whitespace from the next `save` on, and mark the
+    /// store modified so that save happens. Only for JSON stores; opening
+    /// the file again goes back to pretty-printing.
+    pub fn compact_json(&mut self) -> Result<()> {
+        if self.format != Format::Json {
+            return Err(anyhow!("compact_json only applies to JSON data files"));
+        }
+        self.compact_json = true;
+        self.dirty.set(true);
+        Ok(())
+    }
+
+    /// Drop every archived contact and switch to compact JSON, like
+    /// [`Store::compact_json`]. Returns the data file's current size and the
+    /// size the next `save` will write, in bytes.
+    pub fn compact(&mut self) -> Result<(u64, u64)> {
+        if self.path.as_os_str().is_empty() {
+            return Err(anyhow!("compact needs a data file to measure"));
+        }
+        self.compact_json()?;
+        let old_bytes = match fs::metadata(&self.path) {
+            Ok(meta) => meta.len(),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => 0,
+            Err(e) => return Err(e).with_context(|| format!("reading {}", self.path.display())),
+        };
+        self.purge_archived();
+        Ok((old_bytes, self.json_bytes()?.len() as u64))
+    }
+
+    fn write_atomic(&self, path: &Path, data: &[u8]) -> Result<()> {
+        // 1. Make sure the parent directory exists
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("creating parent dir {}", parent.display()))?;
+        }
+
+        // 2. Open (or create) the target file so we can lock it.
+        //    fs2 requires a File handle to apply the lock.
+        //    Never truncate here: the contents are replaced by the rename below.
+        let target_file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(path)
+            .with_context(|| format!("opening/creating target file {}", path.display()))?;
+
+        // 3. Acquire an exclusive lock on the file
+        //    (prevents other processes from writing at the same time).
+        if !self.no_lock {
+            lock_file(&target_file, true).with_context(|| "acquiring exclusive lock for write")?;
+        }
+
+        // 4. Keep the current contents in a `.undo` sidecar so contacts this
+        //    save drops can still be recovered with `unremove`.
+        let mut previous = Vec::new();
+        (&target_file)
+            .read_to_end(&mut previous)
+            .with_context(|| "reading previous data for undo file")?;
+
+        // 5. IMPORTANT: release the file handle and its lock before persisting.
+        //    On Windows, you cannot rename/overwrite a locked file.
+        drop(target_file);
+
+        // 6. Create a secure temporary file in the same directory.
+        //    This ensures atomic save: we write everything to the temp file first.
+        let parent = path
+            .parent()
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| PathBuf::from("."));
+
+        // The undo copy from step 4 is written before the data is replaced.
+        if !previous.is_empty() {
+            write_private(&parent, &sidecar_path(path, "undo"), &previous)?;
+        }
+
+        let mut tmp = NamedTempFile::new_in(&parent)
+            .with_context(|| "creating secure temporary file for atomic write")?;
+
+        // 7. Write the data into the temporary file.
+        tmp.write_all(data)
+            .with_context(|| "writing data to temp file")?;
+
+        // 8. Ensure data is written from buffer to disk.
+        tmp.flush().with_context(|| "flushing temp file")?;
+
+        // 9. On Unix: set file permissions to 600 (owner read/write only).
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o600))
+                .with_context(|| "setting secure permissions on temp file")?;
+        }
+
+        // 10. Sync file to disk to ensure durability.
+        tmp.as_file()
+            .sync_all()
+            .with_context(|| "syncing temp file to disk")?;
+
+        // 11. Record the checksum in a sidecar, in `sha256sum` format, so
+        //     `open` can detect edits or corruption outside this program.
+        //     It goes first: if we stop before step 12, the old data sits
+        //     under the new checksum, and `open` accepts that because the
+        //     old data matches the `.undo` copy. A first save has no undo
+        //     copy, so its checksum waits until the data is in place.
+        let sum = format!("{}  {}\n", digest_hex(data), file_name(path));
+        let sum_path = sidecar_path(path, "sha256");
+        if !previous.is_empty() {
+            write_private(&parent, &sum_path, sum.as_bytes())?;
+        }
+
+        // 12. Atomically replace the original file with the temp file.
+        //     At this point, the original file is unlocked, so Windows won’t complain.
+        tmp.persist(path)
+            .map_err(|e| anyhow!("failed to persist temp file: {}", e))?;
+        if previous.is_empty() {
+            write_private(&parent, &sum_path, sum.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// `<data file>.<ext>`, next to the data file.
+pub(crate) fn sidecar_path(path: &Path, ext: &str) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(ext);
+    PathBuf::from(name)
+}
+
+/// Atomically replace `target` with `data`, readable only by the owner.
+fn write_private(dir: &Path, target: &Path, data: &[u8]) -> Result<()> {
+    let mut tmp = NamedTempFile::new_in(dir)
+        .with_context(|| format!("creating temporary file for {}", target.display()))?;
+    tmp.write_all(data)
+        .with_context(|| format!("writing {}", target.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(tmp.path(), fs::Permissions::from_mode(0o600))
+            .with_context(|| format!("setting secure permissions on {}", target.display()))?;
+    }
+    tmp.persist(target)
+        .map_err(|e| anyhow!("failed to persist {}: {}", target.display(), e))?;
+    Ok(())
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default()
+}
+
+fn digest_hex(data: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(data))
+}
+
+fn check_digest(path: &Path, data: &[u8]) -> Result<()> {
+    let sum_path = sidecar_path(path, "sha256");
+    let recorded = fs::read_to_string(&sum_path)
+        .with_context(|| format!("reading checksum file: {}", sum_path.display()))?;
+    let recorded = recorded.split_whitespace().next().unwrap_or_default();
+    if recorded.eq_ignore_ascii_case(&digest_hex(data)) {
+        return Ok(());
+    }
+    // A save that stopped between writing the checksum and replacing the
+    // data leaves the data as it was, which is also the `.undo` copy.
+    let interrupted_save = fs::read(sidecar_path(path, "undo")).is_ok_and(|undo| undo == data);
+    if !interrupted_save {
+        return Err(anyhow!(
+            "checksum mismatch for {}: the file was modified outside this program \
+             (use --skip-verify to open it anyway)",
+            path.display()
+        ));
+    }
+    Ok(())
+}
+
+fn lock_file(file: &fs::File, exclusive: bool) -> std::io::Result<()> {
+    #[cfg(test)]
+    if tests::LOCKS_UNSUPPORTED.with(Cell::get) {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::Unsupported,
+            "locking not supported",
+        ));
+    }
+    if exclusive {
+        FileExt::lock_exclusive(file)
+    } else {
+        FileExt::lock_shared(file)
+    }
+}
+
+fn parse_contacts(buf: &str) -> Result<Vec<Contact>> {
+    serde_json::from_str(buf).map_err(|e| anyhow!("failed to parse JSON: {}", e))
+}
+
+fn parse_contacts_as(buf: &[u8], format: Format) -> Result<Vec<Contact>> {
+    let text = || std::str::from_utf8(buf).map_err(|e| anyhow!("data file is not UTF-8: {}", e));
+    match format {
+        Format::Json => parse_contacts(text()?),
+        Format::Toml => toml::from_str::<TomlFile>(text()?)
+            .map(|f| f.contacts)
+            .map_err(|e| anyhow!("failed to parse TOML: {}", e)),
+        #[cfg(feature = "bincode-backend")]
+        Format::Binary => {
+            bincode::deserialize(buf).map_err(|e| anyhow!("failed to parse binary data: {}", e))
+        }
+        #[cfg(feature = "msgpack")]
+        Format::Msgpack => {
+            rmp_serde::from_read(buf).map_err(|e| anyhow!("failed to parse MessagePack: {}", e))
+        }
+    }
+}
+
+/// Skipped by [`Store::word_frequency`].
+const STOP_WORDS: &[&str] = &[
+    "a", "an", "and", "at", "by", "for", "in", "of", "on", "or", "the", "to", "with",
+];
+
+/// The text of one field of `c`, for word counts.
+fn field_text(c: &Contact, field: ContactField) -> Vec<String> {
+    let address =
+        |part: fn(&Address) -> &Option<String>| c.address.as_ref().and_then(|a| part(a).clone());
+    match field {
+        ContactField::Name => vec![c.name.clone()],
+        ContactField::Email => c.emails.clone(),
+        ContactField::Phone => c.phone.iter().cloned().collect(),
+        ContactField::Tags => c.tags.clone(),
+        ContactField::Birthday => c.birthday.iter().map(|d| d.to_string()).collect(),
+        ContactField::Anniversary => c.anniversary.iter().map(|d| d.to_string()).collect(),
+        ContactField::Timezone => c.timezone.iter().cloned().collect(),
+        ContactField::Company => c.company.iter().cloned().collect(),
+        ContactField::Street => address(|a| &a.street).into_iter().collect(),
+        ContactField::City => address(|a| &a.city).into_iter().collect(),
+        ContactField::State => address(|a| &a.state).into_iter().collect(),
+        ContactField::PostalCode => address(|a| &a.postal_code).into_iter().collect(),
+        ContactField::Country => address(|a| &a.country).into_iter().collect(),
+    }
+}
+
+/// Case-insensitive substring match on name and primary email; phone
+/// numbers are matched as typed.
+fn matches_query(c: &Contact, q: &str, search_phone: bool) -> bool {
+    let q_lower = q.to_lowercase();
+    c.name.to_lowercase().contains(&q_lower)
+        || c.primary_email().to_lowercase().contains(&q_lower)
+        || (search_phone && c.phone.as_deref().is_some_and(|p| p.contains(q)))
+}
+
+pub(crate) fn email_key(email: &str) -> String {
+    email.trim().to_lowercase()
+}
+
+/// The lowercased part after the last `@`, if there is one.
+fn email_domain(email: &str) -> Option<String> {
+    email.rsplit_once('@').map(|(_, domain)| domain_key(domain))
+}
+
+fn domain_key(domain: &str) -> String {
+    domain.trim().trim_start_matches('@').to_lowercase()
+}
+
+fn company_key(company: &str) -> String {
+    company.trim().to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use tempfile::tempdir;
+
+    thread_local! {
+        /// Makes `lock_file` fail like an unlockable (e.g. NFS) mount.
+        pub(super) static LOCKS_UNSUPPORTED: Cell<bool> = const { Cell::new(false) };
+    }
+
+    #[test]
+    fn add_remove_persist() -> Result<()> {
+        let dir = tempdir()?;
+        let db = dir.path().join("contacts.json");
+        let mut store = Store::open(&db)?.0;
+        assert_eq!(store.contacts().len(), 0);
+        let c = Contact::new("Bob", "bob@example.com", Some("123"))?;
+        let id = c.id.clone();
+        store.add(c);
+        store.save()?;
+        let store2 = Store::open(&db)?.0;
+        assert_eq!(store2.contacts().len(), 1);
+        assert_eq!(store2.contacts()[0].id, id);
+        Ok(())
+    }
+
+    #[test]
+    fn new_does_not_read_disk() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("nested").join("contacts.json");
+        let mut s = Store::new(p.clone());
+        assert!(s.is_empty());
+        assert!(!p.exists());
+
+        s.add(Contact::new("Alice", "alice@example.com", None)?);
+        s.save()?;
+        let reopened = Store::open(&p)?.0;
+        assert_eq!(reopened.contacts(), s.contacts());
+        Ok(())
+    }
+
+    #[test]
+    fn with_path_saves_to_new_file() -> Result<()> {
+        let dir = tempdir()?;
+        let original = dir.path().join("a.json");
+        let copy = dir.path().join("b.json");
+        let mut s = Store::new(original.clone());
+        s.add(Contact::new("Alice", "alice@example.com", None)?);
+        s.save()?;
+
+        let mut s = Store::open(&original)?.0.with_path(&copy);
+        s.add(Contact::new("Bob", "bob@example.com", None)?);
+        s.save()?;
+
+        assert_eq!(Store::open(&original)?.0.total_len(), 1);
+        assert_eq!(Store::open(&copy)?.0.contacts(), s.contacts());
+        Ok(())
+    }
+
+    #[test]
+    fn save_skips_unchanged_store() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        s.add(Contact::new("Alice", "alice@example.com", None)?);
+        s.save()?;
+        assert!(!s.is_dirty());
+        let saved_at = fs::metadata(&p)?.modified()?;
+        std::thread::sleep(std::time::Duration::from_millis(20));
+
+        let mut s = Store::open(&p)?.0;
+        assert!(!s.remove("no-such-id"));
+        s.save()?;
+        assert_eq!(fs::metadata(&p)?.modified()?, saved_at);
+
+        let id = s.contacts()[0].id.clone();
+        assert!(s.remove(&id));
+        assert!(s.is_dirty());
+        s.save()?;
+        assert_ne!(fs::metadata(&p)?.modified()?, saved_at);
+        Ok(())
+    }
+
+    #[test]
+    fn unremove_restores_only_that_contact() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        s.add(Contact::new("Alice", "alice@x.com", Some("123"))?);
+        s.add(Contact::new("Bob", "bob@x.com", None)?);
+        s.save()?;
+        let alice = s.contacts()[0].clone();
+
+        let mut s = Store::open(&p)?.0;
+        s.remove(&alice.id);
+        s.add(Contact::new("Carol", "carol@x.com", None)?);
+        s.save()?;
+
+        let mut s = Store::open(&p)?.0;
+        s.unremove(&alice.id)?;
+        s.save()?;
+        let s = Store::open(&p)?.0;
+        assert_eq!(s.find_by_id(&alice.id), Some(&alice));
+        assert_eq!(s.total_len(), 3);
+
+        let mut s = s;
+        assert!(s.unremove("no-such-id").is_err());
+        assert!(s.unremove(&alice.id).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn open_sets_aside_invalid_ids_with_warning() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let valid = Contact::new("Alice", "alice@x.com", None)?;
+        let json = format!(
+            r#"[{{"id": "not-a-uuid", "name": "Bob", "email": "bob@x.com", "phone": null}},
+                {}]"#,
+            serde_json::to_string(&valid)?
+        );
+        fs::write(&p, json)?;
+
+        let (mut s, warnings) = Store::open(&p)?;
+        assert_eq!(s.contacts(), [valid]);
+        assert_eq!(
+            warnings,
+            [StoreWarning::InvalidId("not-a-uuid".to_string())]
+        );
+
+        s.add(Contact::new("Carol", "carol@x.com", None)?);
+        s.save()?;
+        let (s, warnings) = Store::open(&p)?;
+        assert_eq!(s.total_len(), 2);
+        assert_eq!(warnings.len(), 1);
+        assert!(fs::read_to_string(&p)?.contains("not-a-uuid"));
+        Ok(())
+    }
+
+    #[test]
+    fn open_purges_expired_tombstones() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        let mut old = Contact::new("Old", "old@x.com", None)?;
+        old.archived = true;
+        old.archived_at = Some(Utc::now() - Duration::days(91));
+        let mut recent = Contact::new("Recent", "recent@x.com", None)?;
+        recent.archived = true;
+        recent.archived_at = Some(Utc::now() - Duration::days(89));
+        let active = Contact::new("Active", "active@x.com", None)?;
+        for c in [&old, &recent, &active] {
+            s.add(c.clone());
+        }
+        s.save()?;
+
+        let keep = StoreOptions {
+            tombstone_ttl_days: None,
+            ..Default::default()
+        };
+        assert_eq!(Store::open_with(&p, &keep)?.0.total_len(), 3);
+
+        let s = Store::open(&p)?.0;
+        assert_eq!(s.auto_purged(), 1);
+        assert!(s.find_by_id(&old.id).is_none());
+        assert!(s.find_by_id(&recent.id).is_some());
+        assert!(s.find_by_id(&active.id).is_some());
+        s.save()?;
+        assert_eq!(Store::open_with(&p, &keep)?.0.total_len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn find_without_phone_or_email() -> Result<()> {
+        let mut s = Store::default();
+        s.add(Contact::new("A", "a@x.com", Some("123"))?);
+        s.add(Contact::new("B", "b@x.com", None)?);
+        s.add(Contact::new("C", "c@x.com", Some(""))?);
+        let mut no_email = Contact::new("D", "d@x.com", None)?;
+        no_email.emails.clear();
+        s.add(no_email);
+        let archived = Contact::new("E", "e@x.com", None)?;
+        let id = archived.id.clone();
+        s.add(archived);
+        s.set_archived(&id, true);
+
+        let names = |found: Vec<&Contact>| -> Vec<String> {
+            found.into_iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names(s.find_without_phone()), ["B", "C", "D"]);
+        assert_eq!(names(s.find_without_email()), ["D"]);
+        Ok(())
+    }
+
+    #[test]
+    fn take_returns_contacts_in_order() -> Result<()> {
+        let mut s = Store::default();
+        s.add(Contact::new("A", "a@x.com", None)?);
+        s.add(Contact::new("B", "b@x.com", None)?);
+        let expected = s.contacts().to_vec();
+
+        let mut contacts = s.take();
+        assert_eq!(contacts, expected);
+        contacts.retain(|c| c.name == "B");
+        contacts.push(Contact::new("C", "c@x.com", None)?);
+        assert_eq!(contacts.len(), 2);
+
+        let s = Store::default();
+        assert!(s.into_contacts().is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sample_is_reproducible_with_seed() -> Result<()> {
+        use rand::rngs::StdRng;
+        use rand::SeedableRng;
+
+        let mut s = Store::default();
+        for i in 0..20 {
+            s.add(Contact::new(
+                &format!("C{}", i),
+                &format!("c{}@x.com", i),
+                None,
+            )?);
+        }
+        let ids = |found: Vec<&Contact>| -> Vec<String> {
+            found.into_iter().map(|c| c.id.clone()).collect()
+        };
+        let first = ids(s.sample(5, &mut StdRng::seed_from_u64(7)));
+        let second = ids(s.sample(5, &mut StdRng::seed_from_u64(7)));
+        assert_eq!(first.len(), 5);
+        assert_eq!(first, second);
+
+        let mut all = ids(s.sample(100, &mut StdRng::seed_from_u64(7)));
+        all.sort();
+        let mut expected = ids(s.contacts().iter().collect());
+        expected.sort();
+        assert_eq!(all, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn find_not_is_complement_of_find() -> Result<()> {
+        let mut s = Store::default();
+        s.add(Contact::new("Alice", "alice@x.com", None)?);
+        s.add(Contact::new("Bob", "bob@x.com", None)?);
+        let found = s.find_not("alice");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "Bob");
+        assert_eq!(found.len(), s.len() - s.find("alice", false, false).len());
+        Ok(())
+    }
+
+    #[test]
+    fn counts_split_active_and_archived() -> Result<()> {
+        let mut s = Store::default();
+        let mut ids = Vec::new();
+        for i in 0..5 {
+            let c = Contact::new(&format!("C{}", i), &format!("c{}@x.com", i), None)?;
+            ids.push(c.id.clone());
+            s.add(c);
+        }
+        s.set_archived(&ids[1], true);
+        s.set_archived(&ids[3], true);
+
+        let counts = s.counts();
+        assert_eq!(
+            counts,
+            Counts {
+                active: 3,
+                archived: 2,
+                total: 5
+            }
+        );
+        assert_eq!(counts.active + counts.archived, counts.total);
+        assert_eq!(counts.to_string(), "5 (2 archived)");
+        assert_eq!(Store::default().counts().to_string(), "0");
+        Ok(())
+    }
+
+    #[test]
+    fn cursor_pages_cover_every_contact_once() -> Result<()> {
+        let mut s = Store::default();
+        for i in 0..5 {
+            s.add(Contact::new(&format!("C{}", i), "c@example.com", None)?);
+        }
+        let mut seen = Vec::new();
+        let mut cursor = None;
+        loop {
+            let (page, next) = s.paginate_with_cursor(cursor.as_deref(), 2);
+            assert!(page.len() <= 2);
+            seen.extend(page.iter().map(|c| c.id.clone()));
+            match next {
+                Some(id) => cursor = Some(id),
+                None => break,
+            }
+        }
+        let mut all: Vec<String> = s.contacts().iter().map(|c| c.id.clone()).collect();
+        all.sort();
+        assert_eq!(seen, all);
+        assert_eq!(s.paginate_with_cursor(None, 0), (Vec::new(), None));
+        Ok(())
+    }
+
+    #[test]
+    fn word_frequency_counts_company_words() -> Result<()> {
+        let mut s = Store::default();
+        for company in ["Acme Corp.", "Globex corp", "The Bank of Acme", ""] {
+            let mut c = Contact::new("X", "x@example.com", None)?;
+            c.company = Some(company.to_string()).filter(|c| !c.is_empty());
+            s.add(c);
+        }
+        let words = s.word_frequency(ContactField::Company);
+        assert_eq!(words.get("corp"), Some(&2));
+        assert_eq!(words.get("acme"), Some(&2));
+        assert_eq!(words.get("bank"), Some(&1));
+        assert!(!words.contains_key("the") && !words.contains_key("of"));
+        assert_eq!(words.len(), 4);
+        Ok(())
+    }
+
+    #[test]
+    fn split_by_predicate_partitions_everything() -> Result<()> {
+        let mut s = Store::default();
+        for (name, phone) in [
+            ("A", Some("1")),
+            ("B", None),
+            ("C", Some("2")),
+            ("D", Some("3")),
+            ("E", None),
+        ] {
+            s.add(Contact::new(name, "x@example.com", phone)?);
+        }
+        let (with, without) = s.split_by_predicate(|c| c.phone.is_some());
+        assert_eq!(with.len(), 3);
+        assert_eq!(without.len(), 2);
+        let mut names: Vec<&str> = with
+            .iter()
+            .chain(&without)
+            .map(|c| c.name.as_str())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["A", "B", "C", "D", "E"]);
+        Ok(())
+    }
+
+    #[test]
+    fn anonymized_copy_has_no_real_emails() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let mut s = Store::default();
+        s.add(Contact::new("Alice", "alice@real.org", Some("555-0100"))?);
+        s.add(Contact::new("Bob", "bob@real.org", None)?);
+        let copy = s.anonymized(dir.path().join("anon.json"));
+        copy.save()?;
+
+        let text = fs::read_to_string(dir.path().join("anon.json"))?;
+        assert!(!text.contains("real.org") && !text.contains("Alice"));
+        let copy = Store::open(dir.path().join("anon.json"))?.0;
+        assert_eq!(copy.total_len(), 2);
+        assert_eq!(copy.contacts()[0].primary_email(), "contact_1@example.com");
+        assert_eq!(copy.contacts()[0].phone.as_deref(), Some("+10000000000"));
+        assert_eq!(copy.contacts()[1].phone, None);
+        Ok(())
+    }
+
+    #[test]
+    fn company_lookup_is_exact_and_follows_updates() -> Result<()> {
+        let mut s = Store::default();
+        let mut ids = Vec::new();
+        for (name, company) in [("A", "Acme"), ("B", "acme "), ("C", "Acme Labs")] {
+            let mut c = Contact::new(name, "x@example.com", None)?;
+            c.company = Some(company.into());
+            ids.push(c.id.clone());
+            s.add(c);
+        }
+        let names = |s: &Store, q: &str| -> Vec<String> {
+            s.find_by_company_exact(q)
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        };
+        assert_eq!(names(&s, "ACME"), ["A", "B"]);
+
+        s.remove(&ids[0]);
+        assert_eq!(names(&s, "acme"), ["B"]);
+        let update = ContactUpdate {
+            company: Some("Acme".into()),
+            ..Default::default()
+        };
+        s.update_contact(&ids[2], update)?;
+        assert_eq!(names(&s, "acme"), ["B", "C"]);
+        assert!(names(&s, "Acme Labs").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn sorted_list_orders_by_timestamps() -> Result<()> {
+        let mut s = Store::default();
+        let t0 = Utc::now();
+        for (i, name) in ["Cy", "bob", "Ann"].into_iter().enumerate() {
+            let mut c = Contact::new(name, "x@example.com", None)?;
+            c.created_at = Some(t0 + Duration::minutes(i as i64));
+            c.updated_at = Some(t0 - Duration::minutes(i as i64));
+            s.add(c);
+        }
+        let names = |by, reverse| -> Vec<String> {
+            s.sorted_list(by, reverse)
+                .iter()
+                .map(|c| c.name.clone())
+                .collect()
+        };
+        assert_eq!(names(SortField::Natural, false), ["Cy", "bob", "Ann"]);
+        assert_eq!(names(SortField::Name, false), ["Ann", "bob", "Cy"]);
+        assert_eq!(names(SortField::CreatedAt, false), ["Cy", "bob", "Ann"]);
+        assert_eq!(names(SortField::CreatedAt, true), ["Ann", "bob", "Cy"]);
+        assert_eq!(names(SortField::UpdatedAt, false), ["Ann", "bob", "Cy"]);
+        Ok(())
+    }
+
+    #[test]
+    fn find_first_returns_the_earliest_active_match() -> Result<()> {
+        let mut store = Store::default();
+        for name in ["Bob", "Alice Archived", "Alice", "Alicia", "Malice"] {
+            store.add(Contact::new(name, &format!("{}@x.com", name.len()), None)?);
+        }
+        let archived = store.contacts()[1].id.clone();
+        store.set_archived(&archived, true);
+        assert!(store.find_first("nobody").is_none());
+
+        let all: Vec<&str> = store
+            .find("ali", false, false)
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(all, ["Alice", "Alicia", "Malice"]);
+        assert_eq!(
+            store.find_first("ali").map(|c| c.name.as_str()),
+            Some("Alice")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn email_domain_index_tracks_changes() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice", "alice@acme.com", None)?);
+        store.add(Contact::new("Bob", "bob@ACME.com", None)?);
+        store.add(Contact::new("Carol", "carol@acme.co", None)?);
+
+        let names = |found: Vec<&Contact>| -> Vec<String> {
+            found.iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(
+            names(store.find_by_email_domain("acme.com")),
+            ["Alice", "Bob"]
+        );
+        assert_eq!(
+            names(store.find_by_email_domain("@Acme.Com")),
+            ["Alice", "Bob"]
+        );
+        assert!(store.find_by_email_domain("example.com").is_empty());
+
+        let bob = store.contacts()[1].id.clone();
+        store.update_contact(
+            &bob,
+            ContactUpdate {
+                email: Some("bob@example.com".into()),
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(names(store.find_by_email_domain("acme.com")), ["Alice"]);
+        assert_eq!(names(store.find_by_email_domain("example.com")), ["Bob"]);
+        store.remove(&store.contacts()[0].id.clone());
+        assert!(store.find_by_email_domain("acme.com").is_empty());
+        assert_eq!(names(store.find_by_email_domain("example.com")), ["Bob"]);
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_id_prefix_matches_start_of_id() -> Result<()> {
+        let mut store = Store::default();
+        for (id, name) in [("3f2a01", "A"), ("3f2b02", "B"), ("9c0003", "C")] {
+            let mut c = Contact::new(name, &format!("{}@x.com", id), None)?;
+            c.id = id.into();
+            store.add(c);
+        }
+        let names = |prefix: &str| -> Vec<String> {
+            let found = store.find_by_id_prefix(prefix);
+            found.iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names("3F2A"), ["A"]);
+        assert_eq!(names("3f2"), ["A", "B"]);
+        assert!(names("ab").is_empty());
+        assert!(names("").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn validate_all_reports_contacts_broken_by_new_rules() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice", "alice@x.com", None)?);
+        assert!(store.validate_all().is_empty());
+
+        // Saved before control characters were rejected.
+        let mut bob = Contact::new("Bob", "bob@x.com", None)?;
+        bob.emails[0] = "bob\u{7}@x.com".into();
+        let bob_id = bob.id.clone();
+        store.add(bob);
+
+        let errors = store.validate_all();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].0, bob_id);
+        assert!(errors[0].1.to_string().contains("control characters"));
+        Ok(())
+    }
+
+    #[test]
+    fn find_since_uses_last_modification() -> Result<()> {
+        let mut s = Store::default();
+        let t0 = Utc::now() - Duration::hours(2);
+        let t1 = t0 + Duration::hours(1);
+        for (name, created, updated) in [
+            ("Old", t0, None),
+            ("Edited", t0, Some(t1 + Duration::minutes(5))),
+            ("New", t1 + Duration::minutes(10), None),
+        ] {
+            let mut c = Contact::new(name, "x@example.com", None)?;
+            c.created_at = Some(created);
+            c.updated_at = updated;
+            s.add(c);
+        }
+        let names: Vec<&str> = s.find_since(t1).iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["Edited", "New"]);
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_ids_keeps_argument_order() -> Result<()> {
+        let mut s = Store::default();
+        let mut ids = Vec::new();
+        for name in ["A", "B", "C"] {
+            let c = Contact::new(name, &format!("{}@x.com", name), None)?;
+            ids.push(c.id.clone());
+            s.add(c);
+        }
+        let found = s.find_by_ids(&[&ids[2], "missing", &ids[0]]);
+        let names: Vec<&str> = found.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["C", "A"]);
+        assert!(s.find_by_ids(&[]).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn update_all_edits_every_contact() -> Result<()> {
+        let dir = tempdir()?;
+        let mut s = Store::new(dir.path().join("contacts.json"));
+        s.add(Contact::new("alice", "alice@x.com", None)?);
+        s.add(Contact::new("bob", "bob@x.com", None)?);
+        s.add(Contact::new("CAROL", "carol@x.com", None)?);
+        s.save()?;
+        let before = s.contacts().to_vec();
+
+        assert_eq!(s.update_all(|c| c.name = c.name.to_uppercase()), 2);
+        assert!(s.is_dirty());
+        let names: Vec<&str> = s.contacts().iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["ALICE", "BOB", "CAROL"]);
+        assert_ne!(s.contacts()[0].updated_at, before[0].updated_at);
+        assert_eq!(s.contacts()[2], before[2]);
+
+        s.update_all(|c| c.emails[0] = c.emails[0].replace("x.com", "y.com"));
+        assert!(s.find_by_email("bob@y.com").is_some());
+        Ok(())
+    }
+
+    #[test]
+    fn transaction_rolls_back_on_error() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        s.add(Contact::new("Alice", "alice@x.com", None)?);
+        s.save()?;
+        let before = s.contacts().to_vec();
+
+        let result = s.transaction(|s| {
+            s.add(Contact::new("Bob", "bob@x.com", None)?);
+            s.add(Contact::new("Carol", "carol@x.com", None)?);
+            Err(anyhow!("changed my mind"))
+        });
+        assert!(result.is_err());
+        assert_eq!(s.contacts(), before);
+        assert!(s.find_by_email("bob@x.com").is_none());
+        assert!(!s.is_dirty());
+
+        s.transaction(|s| {
+            s.add(Contact::new("Bob", "bob@x.com", None)?);
+            s.add(Contact::new("Carol", "carol@x.com", None)?);
+            Ok(())
+        })?;
+        assert_eq!(Store::open(&p)?.0.total_len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn no_lock_works_where_locking_fails() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        s.add(Contact::new("Alice", "alice@x.com", None)?);
+        s.save()?;
+
+        LOCKS_UNSUPPORTED.with(|l| l.set(true));
+        let locked = Store::open(&p);
+        let options = StoreOptions {
+            no_lock: true,
+            ..Default::default()
+        };
+        let unlocked = Store::open_with(&p, &options).and_then(|(mut s, _)| {
+            s.add(Contact::new("Bob", "bob@x.com", None)?);
+            s.save()
+        });
+        LOCKS_UNSUPPORTED.with(|l| l.set(false));
+
+        assert!(format!("{:#}", locked.unwrap_err()).contains("locking not supported"));
+        unlocked?;
+        assert_eq!(Store::open(&p)?.0.total_len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn open_existing_requires_the_file() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+
+        assert!(Store::open_existing(&p).is_err());
+        let mut s = Store::open_or_create(&p)?.0;
+        assert!(s.is_empty());
+        assert!(!p.exists());
+        s.add(Contact::new("Alice", "alice@x.com", None)?);
+        s.save()?;
+
+        assert_eq!(Store::open_existing(&p)?.0.total_len(), 1);
+        assert_eq!(Store::open_or_create(&p)?.0.total_len(), 1);
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_all_or_any_tags() -> Result<()> {
+        let mut s = Store::default();
+        let mut both = Contact::new("Both", "both@x.com", None)?;
+        both.add_tag("work")?;
+        both.add_tag("vip")?;
+        let mut personal = Contact::new("Personal", "p@x.com", None)?;
+        personal.add_tag("personal")?;
+        s.add(both);
+        s.add(personal);
+        s.add(Contact::new("None", "none@x.com", None)?);
+
+        let names = |found: Vec<&Contact>| -> Vec<String> {
+            found.into_iter().map(|c| c.name.clone()).collect()
+        };
+        assert_eq!(names(s.find_contains_all_tags(&["work", "vip"])), ["Both"]);
+        assert!(s.find_contains_all_tags(&["work", "personal"]).is_empty());
+        assert_eq!(
+            names(s.find_contains_any_tag(&["vip", "personal"])),
+            ["Both", "Personal"]
+        );
+        assert!(s.find_contains_any_tag(&[]).is_empty());
+        assert_eq!(s.find_contains_all_tags(&[]).len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn check_id_uniqueness_reports_collisions() -> Result<()> {
+        let mut s = Store::default();
+        let a = Contact::new_with_id_format("A", "a@x.com", None, IdFormat::Hash)?;
+        s.add(a.clone());
+        s.add(Contact::new_with_id_format(
+            "B",
+            "b@x.com",
+            None,
+            IdFormat::Short,
+        )?);
+        s.check_id_uniqueness()?;
+
+        s.contacts_mut().push(a.clone());
+        let err = s.check_id_uniqueness().unwrap_err().to_string();
+        assert!(err.contains(&a.id));
+        Ok(())
+    }
+
+    #[test]
+    fn atomic_write_permissions() -> Result<()> {
+        let dir = tempdir()?;
+        let db = dir.path().join("contacts.json");
+        let mut store = Store::open(&db)?.0;
+        store.add(Contact::new("C", "c@d.com", None)?);
+        store.save()?;
+        let meta = fs::metadata(&db)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = meta.permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn toml_round_trip() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.toml");
+        let mut s = Store::open(&p)?.0;
+        let mut c = Contact::new("Alice", "alice@example.com", Some("123"))?;
+        c.tags.push("work".into());
+        c.birthday = chrono::NaiveDate::from_ymd_opt(1990, 5, 1);
+        c.address = Some(crate::contact::Address {
+            city: Some("Oslo".into()),
+            ..Default::default()
+        });
+        c.social_handles.insert("github".into(), "alice".into());
+        s.add(c);
+        s.add(Contact::new("Bob", "bob@example.com", None)?);
+        s.save()?;
+
+        let text = fs::read_to_string(&p)?;
+        assert!(text.contains("[[contacts]]"));
+        assert!(serde_json::from_str::<serde_json::Value>(&text).is_err());
+
+        let reopened = Store::open(&p)?.0;
+        assert_eq!(reopened.contacts(), s.contacts());
+        Ok(())
+    }
+
+    #[cfg(feature = "bincode-backend")]
+    #[test]
+    fn binary_round_trip_is_smaller() -> Result<()> {
+        let dir = tempdir()?;
+        let json = dir.path().join("contacts.json");
+        let bin = dir.path().join("contacts.bin");
+        let mut s = Store::open(&json)?.0;
+        let mut c = Contact::new("Alice", "alice@example.com", Some("123"))?;
+        c.birthday = chrono::NaiveDate::from_ymd_opt(1990, 5, 1);
+        c.social_handles.insert("github".into(), "alice".into());
+        s.add(c);
+        s.add(Contact::new("Bob", "bob@example.com", None)?);
+        s.save()?;
+        s.save_binary(&bin)?;
+
+        assert!(fs::metadata(&bin)?.len() < fs::metadata(&json)?.len());
+        assert_eq!(Store::open_binary(&bin)?.0.contacts(), s.contacts());
+        // `open` recognises the binary file without being told.
+        assert_eq!(Store::open(&bin)?.0.contacts(), s.contacts());
+        Ok(())
+    }
+
+    #[cfg(feature = "msgpack")]
+    #[test]
+    fn msgpack_round_trip_is_smaller() -> Result<()> {
+        let dir = tempdir()?;
+        let json = dir.path().join("contacts.json");
+        let packed = dir.path().join("contacts.msgpack");
+        let mut s = Store::new(json.clone());
+        let mut c = Contact::new("Alice", "alice@example.com", Some("123"))?;
+        c.birthday = chrono::NaiveDate::from_ymd_opt(1990, 5, 1);
+        c.social_handles.insert("github".into(), "alice".into());
+        s.add(c);
+        s.add(Contact::new("Bob", "bob@example.com", None)?);
+        s.save()?;
+        let mut m = Store::empty(&packed, Format::Msgpack);
+        for c in s.contacts() {
+            m.add(c.clone());
+        }
+        m.save()?;
+
+        assert!(fs::metadata(&packed)?.len() < fs::metadata(&json)?.len());
+        // `open` recognises the MessagePack file without being told.
+        let reopened = Store::open(&packed)?.0;
+        assert_eq!(reopened.contacts(), s.contacts());
+        assert_eq!(reopened.format, Format::Msgpack);
+        Ok(())
+    }
+
+    #[test]
+    fn compact_json_is_smaller_and_round_trips() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        s.add(Contact::new("Alice", "alice@example.com", Some("123"))?);
+        s.add(Contact::new("Bob", "bob@example.com", None)?);
+        s.save()?;
+        let pretty = fs::metadata(&p)?.len();
+
+        s.compact_json()?;
+        s.save()?;
+        assert!(fs::metadata(&p)?.len() < pretty);
+        assert!(!fs::read_to_string(&p)?.contains('\n'));
+        assert_eq!(Store::open(&p)?.0.contacts(), s.contacts());
+        Ok(())
+    }
+
+    #[test]
+    fn compact_drops_archived_and_shrinks_the_file() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        for i in 0..100 {
+            s.add(Contact::new(
+                &format!("P{}", i),
+                &format!("p{}@x.com", i),
+                None,
+            )?);
+        }
+        let archived = s.contacts()[0].id.clone();
+        s.set_archived(&archived, true);
+        s.save()?;
+        let pretty = fs::metadata(&p)?.len();
+
+        let (old, new) = s.compact()?;
+        assert_eq!(old, pretty);
+        assert!(new < old);
+        s.save()?;
+        assert_eq!(new, fs::metadata(&p)?.len());
+        let reopened = Store::open(&p)?.0;
+        assert_eq!(reopened.total_len(), 99);
+        assert!(reopened.find_by_id(&archived).is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_detects_tampering() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::open(&p)?.0;
+        s.add(Contact::new("Alice", "alice@example.com", None)?);
+        s.save()?;
+        assert!(dir.path().join("contacts.json.sha256").exists());
+        s.verify_checksum()?;
+
+        // Flip one byte without updating the sidecar.
+        let mut data = fs::read(&p)?;
+        let i = data.iter().position(|&b| b == b'A').unwrap();
+        data[i] = b'B';
+        fs::write(&p, &data)?;
+
+        assert!(s.verify_checksum().is_err());
+        let err = Store::open(&p).unwrap_err();
+        assert!(err.to_string().contains("checksum mismatch"));
+
+        let opts = StoreOptions {
+            skip_verify: true,
+            ..Default::default()
+        };
+        let s = Store::open_with(&p, &opts)?.0;
+        assert_eq!(s.contacts()[0].name, "Blice");
+        Ok(())
+    }
+
+    #[test]
+    fn checksum_from_an_interrupted_save_is_accepted() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::new(p.clone());
+        s.add(Contact::new("Alice", "alice@example.com", None)?);
+        s.save()?;
+
+        // A save that wrote the new checksum and the undo copy, then stopped
+        // before replacing the data.
+        fs::copy(&p, dir.path().join("contacts.json.undo"))?;
+        let sum = format!("{}  contacts.json\n", digest_hex(b"[]"));
+        fs::write(dir.path().join("contacts.json.sha256"), sum)?;
+        assert_eq!(Store::open(&p)?.0.total_len(), 1);
+        s.verify_checksum()?;
+
+        // Anything else under a stale checksum is still refused.
+        fs::write(&p, "[]\n")?;
+        assert!(Store::open(&p).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn max_file_size_is_inclusive() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        fs::write(&p, format!("[]{}", " ".repeat(98)))?;
+        let limit = |max| StoreOptions {
+            max_file_size: Some(max),
+            ..Default::default()
+        };
+
+        assert!(Store::open_with(&p, &limit(100))?.0.is_empty());
+        let err = Store::open_with(&p, &limit(99)).unwrap_err();
+        assert!(err.to_string().contains("over the 99-byte limit"));
+        Ok(())
+    }
+
+    #[test]
+    fn reader_and_writer_round_trip() -> Result<()> {
+        let empty = Store::from_reader("  \n".as_bytes())?;
+        assert!(empty.contacts().is_empty());
+
+        let mut store = Store::from_reader("[]".as_bytes())?;
+        store.add(Contact::new("Alice", "a@x.com", None)?);
+        let mut out = Vec::new();
+        store.write_json(&mut out)?;
+        let back = Store::from_reader(out.as_slice())?;
+        assert_eq!(back.contacts(), store.contacts());
+        assert!(Store::from_reader("not json".as_bytes()).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn get_by_position() -> Result<()> {
+        let mut s = Store::default();
+        assert!(s.get(0).is_none());
+        s.add(Contact::new("Alice", "alice@x.com", None)?);
+        s.add(Contact::new("Bob", "bob@x.com", None)?);
+        assert_eq!(s.get(1).map(|c| c.name.as_str()), Some("Bob"));
+        assert!(s.get(2).is_none());
+
+        s.contacts_mut().swap(0, 1);
+        s.reindex();
+        assert_eq!(s.get(0).map(|c| c.name.as_str()), Some("Bob"));
+        assert_eq!(s.find_by_email("alice@x.com"), s.get(1));
+        Ok(())
+    }
+
+    #[test]
+    fn head_and_tail_slices() -> Result<()> {
+        let mut s = Store::default();
+        assert!(s.head(3).is_empty());
+        for name in ["A", "B", "C", "D", "E"] {
+            s.add(Contact::new(name, &format!("{}@x.com", name), None)?);
+        }
+        let names = |cs: &[Contact]| cs.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(s.head(3)), ["A", "B", "C"]);
+        assert_eq!(names(s.tail(3)), ["C", "D", "E"]);
+        assert_eq!(s.head(10).len(), 5);
+        assert_eq!(s.tail(10).len(), 5);
+        Ok(())
+    }
+
+    #[test]
+    fn find_works() -> Result<()> {
+        let mut store = Store {
+            contacts: vec![],
+            path: PathBuf::from(""),
+            ..Default::default()
+        };
+        store.add(Contact::new("Alice Smith", "alice@x.com", None)?);
+        store.add(Contact::new("Bob Brown", "bob@x.com", None)?);
+        let f = store.find("alice", false, false);
+        assert_eq!(f.len(), 1);
+        let f2 = store.find("@x.com", false, false);
+        assert_eq!(f2.len(), 2);
+        Ok(())
+    }
+
+    #[test]
+    fn lengths_separate_archived() -> Result<()> {
+        let mut store = Store::default();
+        assert!(store.is_empty());
+        assert_eq!(
+            (store.len(), store.total_len(), store.archived_len()),
+            (0, 0, 0)
+        );
+
+        let mut ids = Vec::new();
+        for name in ["A", "B", "C"] {
+            let c = Contact::new(name, "x@x.com", None)?;
+            ids.push(c.id.clone());
+            store.add(c);
+        }
+        store.set_archived(&ids[0], true);
+        assert_eq!(
+            (store.len(), store.total_len(), store.archived_len()),
+            (2, 3, 1)
+        );
+        assert!(!store.is_empty());
+
+        store.set_archived(&ids[1], true);
+        store.set_archived(&ids[2], true);
+        assert!(store.is_empty());
+        assert_eq!(store.total_len(), 3);
+        Ok(())
+    }
+
+    #[test]
+    fn purge_removes_only_archived() -> Result<()> {
+        let dir = tempdir()?;
+        let p = dir.path().join("contacts.json");
+        let mut s = Store::open(&p)?.0;
+        for i in 0..5 {
+            s.add(Contact::new(
+                &format!("User {}", i),
+                &format!("u{}@x.com", i),
+                None,
+            )?);
+        }
+        let ids: Vec<String> = s.contacts().iter().map(|c| c.id.clone()).collect();
+        for id in &ids[..3] {
+            s.set_archived(id, true);
+        }
+
+        assert_eq!(s.purge_archived(), 3);
+        assert_eq!(s.total_len(), 2);
+        s.save()?;
+
+        let s = Store::open(&p)?.0;
+        assert_eq!(s.total_len(), 2);
+        assert!(ids[..3].iter().all(|id| s.find_by_id(id).is_none()));
+        assert_eq!(s.archived_len(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn find_searches_phone_only_when_asked() -> Result<()> {
+        let mut store = Store::default();
+        store.add(Contact::new("Alice", "alice@x.com", Some("+1 555 0100"))?);
+        store.add(Contact::new("Bob", "bob@x.com", None)?);
+        assert!(store.find("555", false, false).is_empty());
+        let found = store.find("555", false, true);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "Alice");
+        Ok(())
+    }
+
+    #[test]
+    fn find_skips_archived_by_default() -> Result<()> {
+        let mut store = Store::default();
+        let alice = Contact::new("Alice", "alice@x.com", None)?;
+        let id = alice.id.clone();
+        store.add(alice);
+        store.add(Contact::new("Alicia", "alicia@x.com", None)?);
+
+        assert!(store.set_archived(&id, true));
+        assert!(!store.set_archived("missing", true));
+        assert_eq!(store.find("ali", false, false).len(), 1);
+        assert_eq!(store.find("ali", true, false).len(), 2);
+        assert!(store.find("alice@", false, false).is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_id_lookups() -> Result<()> {
+        let mut store = Store::default();
+        let a = Contact::new("Alice", "alice@x.com", None)?;
+        let b = Contact::new("Bob", "bob@x.com", None)?;
+        let (a_id, b_id) = (a.id.clone(), b.id.clone());
+        store.add(a);
+        store.add(b);
+
+        assert_eq!(
+            store.find_by_id(&b_id).map(|c| c.name.as_str()),
+            Some("Bob")
+        );
+        assert!(store.find_by_id("no-such-id").is_none());
+
+        store.find_by_id_mut(&a_id).unwrap().name = "Alicia".into();
+        assert_eq!(store.contacts()[0].name, "Alicia");
+        assert!(store.find_by_id_mut("no-such-id").is_none());
+
+        // Removing shifts positions; the index must follow.
+        assert!(store.remove(&a_id));
+        assert!(!store.remove(&a_id));
+        assert_eq!(
+            store.find_by_id(&b_id).map(|c| c.name.as_str()),
+            Some("Bob")
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn update_contact_by_id() -> Result<()> {
+        let mut store = Store::default();
+        let c = Contact::new("Alice", "alice@x.com", None)?;
+        let id = c.id.clone();
+        store.add(c);
+
+        let update = ContactUpdate {
+            email: Some("alice@y.com".into()),
+            timezone: Some("Europe/Paris".into()),
+            ..Default::default()
+        };
+        assert!(store.update_contact(&id, update)?);
+        assert_eq!(store.contacts()[0].primary_email(), "alice@y.com");
+        assert_eq!(store.contacts()[0].name, "Alice");
+        assert!(store.find_by_email("ALICE@y.com").is_some());
+        assert!(store.find_by_email("alice@x.com").is_none());
+        assert!(!store.update_contact("missing", ContactUpdate::default())?);
+        Ok(())
+    }
+
+    #[test]
+    fn find_by_social_platform_and_handle() -> Result<()> {
+        let mut store = Store::default();
+        let mut alice = Contact::new("Alice", "alice@x.com", None)?;
+        alice.apply(ContactUpdate {
+            social_handles: [("github", "alice"), ("twitter", "@al")]
+                .into_iter()
+                .map(|(p, h)| (p.to_string(), h.to_string()))
+                .collect(),
+            ..Default::default()
+        })?;
+        store.add(alice);
+        let mut bob = Contact::new("Bob", "bob@x.com", None)?;
+        bob.apply(ContactUpdate {
+            social_handles: [("twitter".to_string(), "alice".to_string())].into(),
+            ..Default::default()
+        })?;
+        store.add(bob);
+
+        let names = |v: Vec<&Contact>| v.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(store.find_by_social("GitHub", "Alice")), ["Alice"]);
+        assert_eq!(names(store.find_by_social("twitter", "alice")), ["Bob"]);
+        assert!(store.find_by_social("mastodon", "alice").is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn find_in_date_range_bounds() -> Result<()> {
+        let at = |s: &str| s.parse::<DateTime<Utc>>().unwrap();
+        let mut store = Store::default();
+        for (name, created) in [
+            ("Jan", "2024-01-15T00:00:00Z"),
+            ("Mar", "2024-03-15T00:00:00Z"),
+            ("May", "2024-05-15T00:00:00Z"),
+        ] {
+            let mut c = Contact::new(name, "x@x.com", None)?;
+            c.created_at = Some(at(created));
+            store.add(c);
+        }
+        let mut legacy = Contact::new("Legacy", "l@x.com", None)?;
+        legacy.created_at = None;
+        store.add(legacy);
+
+        let names = |v: Vec<&Contact>| v.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        let feb = Some(at("2024-02-01T00:00:00Z"));
+        let apr = Some(at("2024-04-01T00:00:00Z"));
+
+        assert_eq!(names(store.find_in_date_range(feb, None)), ["Mar", "May"]);
+        assert_eq!(names(store.find_in_date_range(None, apr)), ["Jan", "Mar"]);
+        assert_eq!(names(store.find_in_date_range(feb, apr)), ["Mar"]);
+        assert_eq!(store.find_in_date_range(None, None).len(), 4);
+        Ok(())
+    }
+
+    fn tagged(name: &str, tags: &[&str]) -> Result<Contact> {
+        let mut c = Contact::new(name, &format!("{}@x.com", name.to_lowercase()), None)?;
+        for t in tags {
+            c.add_tag(t)?;
+        }
+        Ok(c)
+    }
+
+    #[test]
+    fn rename_tag_updates_all_contacts() -> Result<()> {
+        let mut store = Store::default();
+        store.add(tagged("A", &["wrok", "vip"])?);
+        store.add(tagged("B", &["wrok", "work"])?);
+        store.add(tagged("C", &["family"])?);
+        for c in &mut store.contacts {
+            c.updated_at = None;
+        }
+
+        assert!(store.rename_tag("wrok", " ").is_err());
+        assert!(store.rename_tag("wrok", &"x".repeat(51)).is_err());
+        assert_eq!(store.contacts()[0].tags, vec!["wrok", "vip"]);
+        assert_eq!(store.rename_tag("wrok", "work")?, 2);
+        assert_eq!(store.contacts()[0].tags, vec!["work", "vip"]);
+        assert_eq!(store.contacts()[1].tags, vec!["work"]);
+        assert_eq!(store.contacts()[2].tags, vec!["family"]);
+        assert_eq!(store.rename_tag("wrok", "work")?, 0);
+        assert!(store.contacts()[1].updated_at.is_some());
+        assert_eq!(store.contacts()[2].updated_at, None);
+
+        store.dirty.set(false);
+        assert_eq!(store.rename_tag("work", " work ")?, 0);
+        assert!(!store.is_dirty());
+        Ok(())
+    }
+
+    #[test]
+    fn bulk_tag_counts_only_changes() -> Result<()> {
+        let mut store = Store::default();
+        for name in ["A", "B", "C", "D", "E"] {
+            store.add(tagged(name, &[])?);
+        }
+        let ids: Vec<String> = store.contacts()[..3].iter().map(|c| c.id.clone()).collect();
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+
+        assert_eq!(store.bulk_tag(&ids, "imported")?, 3);
+        assert!(store.is_dirty());
+        assert_eq!(store.bulk_tag(&ids, "imported")?, 0);
+        assert_eq!(store.bulk_tag(&["missing"], "imported")?, 0);
+        assert!(store.bulk_tag(&ids, " ").is_err());
+        let tagged: Vec<&str> = store
+            .find_contains_all_tags(&["imported"])
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(tagged, ["A", "B", "C"]);
+        Ok(())
+    }
+
+    #[test]
+    fn delete_tag_keeps_other_tags() -> Result<()> {
+        let mut store = Store::default();
+        store.add(tagged("A", &["work", "vip"])?);
+        store.add(tagged("B", &["family"])?);
+        for c in &mut store.contacts {
+            c.updated_at = None;
+        }
+
+        assert_eq!(store.delete_tag("vip"), 1);
+        assert_eq!(store.contacts()[0].tags, vec!["work"]);
+        assert_eq!(store.contacts()[1].tags, vec!["family"]);
+        assert!(store.contacts()[0].updated_at.is_some());
+        assert_eq!(store.contacts()[1].updated_at, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Change detection for the `watch` command.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::{Duration, Instant, SystemTime};
+
+/// Detects modifications of a file by comparing its mtime and size, so no
+/// platform-specific notification API is needed. A missing file counts as a
+/// state of its own (creating or deleting the file is a change).
+pub struct FileWatcher {
+    path: PathBuf,
+    last: Option<(SystemTime, u64)>,
+}
+
+impl FileWatcher {
+    /// Start from the file's current state; only later modifications count.
+    pub fn new(path: impl AsRef<Path>) -> Self {
+        let path = path.as_ref().to_path_buf();
+        let last = signature(&path);
+        FileWatcher { path, last }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// True if the file changed since the previous call (or since `new`).
+    pub fn changed(&mut self) -> bool {
+        let now = signature(&self.path);
+        if now == self.last {
+            return false;
+        }
+        self.last = now;
+        true
+    }
+
+    /// Poll every `interval` until the file changes (returns `true`) or
+    /// `timeout` elapses (returns `false`). `None` waits forever.
+    pub fn wait_for_change(&mut self, interval: Duration, timeout: Option<Duration>) -> bool {
+        let start = Instant::now();
+        loop {
+            if self.changed() {
+                return true;
+            }
+            if timeout.is_some_and(|t| start.elapsed() >= t) {
+                return false;
+            }
+            thread::sleep(interval);
+        }
+    }
+
+    /// Block on OS file notifications, calling `on_change` after each real
+    /// change. Watches the parent directory because saves replace the file.
+    #[cfg(feature = "watch-native")]
+    pub fn watch_native(
+        &mut self,
+        mut on_change: impl FnMut() -> anyhow::Result<()>,
+    ) -> anyhow::Result<()> {
+        use anyhow::Context;
+        use notify::{RecursiveMode, Watcher};
+
+        let dir = match self.path.parent() {
+            Some(p) if !p.as_os_str().is_empty() => p.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let (tx, rx) = std::sync::mpsc::channel();
+        let mut watcher =
+            notify::recommended_watcher(tx).with_context(|| "starting file watcher")?;
+        watcher
+            .watch(&dir, RecursiveMode::NonRecursive)
+            .with_context(|| format!("watching {}", dir.display()))?;
+        for event in rx {
+            event.with_context(|| "file watcher error")?;
+            if self.changed() {
+                on_change()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn signature(path: &Path) -> Option<(SystemTime, u64)> {
+    let meta = fs::metadata(path).ok()?;
+    Some((meta.modified().ok()?, meta.len()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Contact, Store};
+    use anyhow::Result;
+    use tempfile::tempdir;
+
+    #[test]
+    fn detects_write_from_another_thread() -> Result<()> {
+        let dir = tempdir()?;
+        let db = dir.path().join("contacts.json");
+        Store::new(db.clone()).save()?;
+
+        let mut watcher = FileWatcher::new(&db);
+        assert!(!watcher.changed());
+
+        let writer_db = db.clone();
+        let writer = thread::spawn(move || -> Result<()> {
+            thread::sleep(Duration::from_millis(50));
+            let mut store = Store::open(&writer_db)?.0;
+            store.add(Contact::new("Alice", "alice@x.com", None)?);
+            store.save()
+        });
+
+        let triggered =
+            watcher.wait_for_change(Duration::from_millis(10), Some(Duration::from_secs(5)));
+        writer.join().unwrap()?;
+        assert!(triggered);
+        assert_eq!(Store::open(&db)?.0.contacts().len(), 1);
+        assert!(!watcher.wait_for_change(Duration::from_millis(5), Some(Duration::from_millis(20))));
+        Ok(())
+    }
+}
@@ -0,0 +1,1199 @@
+use assert_cmd::Command;
+use predicates::prelude::*;
+use std::path::Path;
+use tempfile::tempdir;
+
+fn contacts() -> Command {
+    Command::cargo_bin("secure_contacts").unwrap()
+}
+
+/// `contacts --file <db> <args...>`
+fn contacts_at(db: &Path, args: &[&str]) -> Command {
+    let mut cmd = contacts();
+    cmd.arg("--file").arg(db).args(args);
+    cmd
+}
+
+#[test]
+fn pipe_mode_writes_updated_json_to_stdout() {
+    let out = contacts()
+        .args(["--file", "-", "add", "Alice", "a@x.com"])
+        .write_stdin("[]")
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("Adding contact: Alice"))
+        .get_output()
+        .stdout
+        .clone();
+
+    let json: serde_json::Value = serde_json::from_slice(&out).unwrap();
+    let list = json.as_array().unwrap();
+    assert_eq!(list.len(), 1);
+    assert_eq!(list[0]["name"], "Alice");
+    assert_eq!(list[0]["emails"][0], "a@x.com");
+}
+
+#[test]
+fn pipe_mode_read_only_commands_print_normally() {
+    contacts()
+        .args(["--file", "-", "list"])
+        .write_stdin(r#"[{"id": "1", "name": "Bob", "email": "b@x.com", "phone": null}]"#)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 | Bob | b@x.com"))
+        .stdout(predicate::str::contains("Total: 1"));
+}
+
+#[test]
+fn list_on_empty_store_says_so() {
+    contacts()
+        .args(["--file", "-", "list"])
+        .write_stdin("[]")
+        .assert()
+        .success()
+        .stdout("Address book is empty.\n");
+}
+
+#[test]
+fn missing_id_fails_with_stderr_only() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    for cmd in ["remove", "archive", "unarchive", "update"] {
+        contacts_at(&db, &[cmd, "no-such-id"])
+            .assert()
+            .code(1)
+            .stdout("")
+            .stderr(predicate::str::contains("No contact with id no-such-id"));
+    }
+    assert!(!db.exists());
+    // Read-only commands need the file to exist.
+    contacts_at(&db, &["show", "no-such-id"])
+        .assert()
+        .code(1)
+        .stdout("")
+        .stderr(predicate::str::contains("does not exist"));
+}
+
+#[test]
+fn success_exits_zero() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Saved."))
+        .stderr("");
+    contacts_at(&db, &["find", "nobody"])
+        .assert()
+        .success()
+        .stdout("Found: 0\n");
+}
+
+#[test]
+fn batch_with_failures_saves_the_rest_and_exits_one() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add A a@x.com\nremove nope\n")
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("Batch: 1 succeeded, 1 failed"))
+        .stderr(predicate::str::contains("No contact with id nope"));
+    contacts_at(&db, &["list"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Total: 1"));
+}
+
+#[test]
+fn no_color_env_disables_ansi() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+
+    let has_ansi = |out: &[u8]| out.contains(&0x1b);
+    let forced = contacts_at(&db, &["list", "--color"]).output().unwrap();
+    assert!(has_ansi(&forced.stdout));
+
+    let plain = contacts_at(&db, &["list"])
+        .env("NO_COLOR", "1")
+        .output()
+        .unwrap();
+    assert!(plain.status.success());
+    assert!(!has_ansi(&plain.stdout));
+    assert!(String::from_utf8_lossy(&plain.stdout).contains("Alice"));
+
+    let flagged = contacts_at(&db, &["list", "--no-color"]).output().unwrap();
+    assert!(!has_ansi(&flagged.stdout));
+}
+
+#[test]
+fn color_scheme_none_matches_no_color() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    let run = |args: &[&str]| {
+        contacts_at(&db, args)
+            .env("XDG_CONFIG_HOME", dir.path())
+            .output()
+            .unwrap()
+            .stdout
+    };
+
+    let none = run(&["list", "--color", "--color-scheme", "none"]);
+    assert_eq!(none, run(&["list", "--no-color"]));
+    assert!(!none.contains(&0x1b));
+
+    let id = String::from_utf8(none).unwrap()[..36].to_string();
+    let dark = String::from_utf8(run(&["show", &id, "--color"])).unwrap();
+    let light =
+        String::from_utf8(run(&["show", &id, "--color", "--color-scheme", "light"])).unwrap();
+    assert!(dark.contains("\x1b[36m"));
+    assert!(light.contains("\x1b[34m") && !light.contains("\x1b[36m"));
+}
+
+#[test]
+fn import_without_tty_prints_only_summary() {
+    let dir = tempdir().unwrap();
+    let src = dir.path().join("src.json");
+    let db = dir.path().join("contacts.json");
+    let lines: String = (0..250)
+        .map(|i| format!("add \"User {}\" u{}@x.com\n", i, i))
+        .collect();
+    contacts_at(&src, &["batch"])
+        .write_stdin(lines)
+        .assert()
+        .success();
+
+    for args in [&["import", "--quiet"][..], &["import"][..]] {
+        let mut args = args.to_vec();
+        args.push(src.to_str().unwrap());
+        let out = contacts_at(&db, &args).output().unwrap();
+        assert!(out.status.success());
+        assert!(!String::from_utf8_lossy(&out.stdout).contains("Importing contacts"));
+        assert!(out.stderr.is_empty());
+    }
+    contacts_at(&db, &["list"])
+        .assert()
+        .stdout(predicate::str::contains("Total: 500"));
+}
+
+#[test]
+fn list_output_separator_matches_tsv_and_csv() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com", "-p", "123"])
+        .assert()
+        .success();
+    contacts_at(&db, &["add", "Bob", "bob@x.com", "-p", "456"])
+        .assert()
+        .success();
+
+    let rows = |args: &[&str]| {
+        let out = contacts_at(&db, args).output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stdout).unwrap()
+    };
+    let list = |sep: &str| {
+        let text = rows(&["list", "--no-color", "--output-separator", sep]);
+        let lines: Vec<String> = text.lines().map(String::from).collect();
+        assert_eq!(lines.last().unwrap(), "Total: 2");
+        lines[..lines.len() - 1].to_vec()
+    };
+    let export = |format: &str| {
+        let text = rows(&[
+            "export",
+            "--format",
+            format,
+            "--fields",
+            "id,name,email,phone",
+        ]);
+        text.lines().skip(1).map(String::from).collect::<Vec<_>>()
+    };
+
+    assert_eq!(list("\\t"), export("tsv"));
+    assert_eq!(list(","), export("csv"));
+}
+
+#[test]
+fn grep_flag_matches_find() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    contacts_at(&db, &["add", "Bob", "bob@x.com"])
+        .assert()
+        .success();
+
+    let find = contacts_at(&db, &["find", "alice"]).output().unwrap();
+    let grep = contacts_at(&db, &["--grep", "alice"]).output().unwrap();
+    assert!(grep.status.success());
+    assert_eq!(grep.stdout, find.stdout);
+    let short = contacts_at(&db, &["-g", "alice"]).output().unwrap();
+    assert_eq!(short.stdout, find.stdout);
+
+    let after = contacts_at(&db, &["find", "-g", "alice"]).output().unwrap();
+    assert_eq!(after.stdout, find.stdout);
+
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Alan alan@x.com --tag work\n")
+        .assert()
+        .success();
+    let both = contacts_at(&db, &["find", "--all-tags", "work", "--grep", "al"])
+        .output()
+        .unwrap();
+    assert!(both.status.success());
+    let both = String::from_utf8(both.stdout).unwrap();
+    assert!(both.contains("Alan") && !both.contains("Alice"));
+
+    contacts_at(&db, &["find", "bob", "-g", "alice"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("give one or the other"));
+    contacts_at(&db, &["list", "-g", "alice"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "--grep can only be combined with find",
+        ));
+}
+
+#[test]
+fn purge_requires_confirm_without_terminal() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Alice alice@x.com\nadd Bob bob@x.com\n")
+        .assert()
+        .success();
+    let list = contacts_at(&db, &["list", "--no-color"]).output().unwrap();
+    let id = String::from_utf8(list.stdout).unwrap()[..36].to_string();
+    contacts_at(&db, &["archive", &id]).assert().success();
+
+    contacts_at(&db, &["purge"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--confirm"));
+    contacts_at(&db, &["purge", "--confirm"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Purged 1"));
+    contacts_at(&db, &["list", "--include-archived"])
+        .assert()
+        .stdout(predicate::str::contains("Total: 1"));
+}
+
+#[test]
+fn init_refuses_existing_file_without_force() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["init"]).assert().success();
+    assert_eq!(std::fs::read_to_string(&db).unwrap(), "[]");
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&db).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    contacts_at(&db, &["init", "--template"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("--force"));
+    contacts_at(&db, &["init", "--template", "--force"])
+        .assert()
+        .success();
+    contacts_at(&db, &["list"])
+        .assert()
+        .stdout(predicate::str::contains("Example Person"));
+}
+
+#[test]
+fn remove_confirm_needs_terminal_or_yes() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    let list = contacts_at(&db, &["list", "--no-color"]).output().unwrap();
+    let id = String::from_utf8(list.stdout).unwrap()[..36].to_string();
+
+    contacts_at(&db, &["remove", &id, "--confirm"])
+        .write_stdin("y\n")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("cannot confirm: not a terminal"));
+    contacts_at(&db, &["remove", &id, "--confirm", "-y"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Removed contact"));
+    contacts_at(&db, &["list"])
+        .assert()
+        .stdout(predicate::str::contains("Address book is empty."));
+}
+
+#[test]
+fn rename_changes_only_name_and_updated_at() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alcie", "alice@x.com", "-p", "123"])
+        .assert()
+        .success();
+    let read =
+        || -> serde_json::Value { serde_json::from_slice(&std::fs::read(&db).unwrap()).unwrap() };
+    let before = read()[0].clone();
+    let id = before["id"].as_str().unwrap().to_string();
+
+    contacts_at(&db, &["rename", &id, "Alice"])
+        .assert()
+        .success();
+    let mut after = read()[0].clone();
+    assert_eq!(after["name"], "Alice");
+    assert_ne!(after["updated_at"], before["updated_at"]);
+    after["name"] = before["name"].clone();
+    after["updated_at"] = before["updated_at"].clone();
+    assert_eq!(after, before);
+
+    contacts_at(&db, &["rename", "nope", "Bob"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains(
+            "Contact 'nope' not found; try 'contacts list' to see valid IDs.",
+        ));
+}
+
+#[test]
+fn export_append_writes_header_once() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    let out = dir.path().join("log.csv");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Alice alice@x.com\nadd Bob bob@x.com\n")
+        .assert()
+        .success();
+
+    let export = [
+        "export",
+        "--append",
+        "--format",
+        "csv",
+        "-o",
+        out.to_str().unwrap(),
+    ];
+    contacts_at(&db, &export).assert().success();
+    contacts_at(&db, &export).assert().success();
+    let text = std::fs::read_to_string(&out).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+    assert_eq!(lines.len(), 1 + 2 * 2);
+    assert!(lines[0].starts_with("id,name,"));
+    assert_eq!(lines.iter().filter(|l| l.starts_with("id,")).count(), 1);
+
+    let json = [
+        "export",
+        "--append",
+        "--format",
+        "json",
+        "-o",
+        out.to_str().unwrap(),
+    ];
+    contacts_at(&db, &json).assert().failure();
+}
+
+#[test]
+fn profiles_use_separate_data_files() {
+    let dir = tempdir().unwrap();
+    let with_env = |args: &[&str]| {
+        let mut cmd = contacts();
+        cmd.env("XDG_DATA_HOME", dir.path().join("data"))
+            .env("XDG_CONFIG_HOME", dir.path().join("config"))
+            .current_dir(dir.path())
+            .args(args);
+        cmd
+    };
+    let profiles = dir.path().join("data/contacts/profiles");
+
+    with_env(&["--profile", "work", "add", "Alice", "alice@work.com"])
+        .assert()
+        .success();
+    assert!(profiles.join("work.json").exists());
+    with_env(&["profile", "create", "personal"])
+        .assert()
+        .success();
+    with_env(&["--profile", "personal", "list"])
+        .assert()
+        .success()
+        .stdout("Address book is empty.\n");
+
+    with_env(&["profile", "default", "work"]).assert().success();
+    with_env(&["profile", "list"])
+        .assert()
+        .stdout("  personal\n* work\n");
+    with_env(&["list"])
+        .assert()
+        .stdout(predicate::str::contains("Alice"));
+
+    with_env(&["profile", "delete", "work"]).assert().success();
+    assert!(!profiles.join("work.json").exists());
+    with_env(&["--profile", "../x", "list"]).assert().failure();
+}
+
+#[test]
+fn random_with_seed_is_repeatable() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    let lines: String = (0..10)
+        .map(|i| format!("add User{} u{}@x.com\n", i, i))
+        .collect();
+    contacts_at(&db, &["batch"])
+        .write_stdin(lines)
+        .assert()
+        .success();
+
+    let random = |n: &str| {
+        let out = contacts_at(&db, &["random", "-n", n, "--seed", "42", "--no-color"])
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stdout).unwrap()
+    };
+    assert_eq!(random("3"), random("3"));
+    assert_eq!(random("3").lines().count(), 3);
+    assert_eq!(random("50").lines().count(), 10);
+}
+
+#[test]
+fn export_ids_in_given_order() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Alice alice@x.com\nadd Bob bob@x.com\nadd Carol carol@x.com\n")
+        .assert()
+        .success();
+    let list = contacts_at(&db, &["list", "--no-color"]).output().unwrap();
+    let ids: Vec<String> = String::from_utf8(list.stdout)
+        .unwrap()
+        .lines()
+        .take(3)
+        .map(|l| l[..36].to_string())
+        .collect();
+
+    let wanted = format!("{},nope,{}", ids[2], ids[0]);
+    let out = contacts_at(&db, &["export", "--ids", &wanted])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    let names: Vec<&str> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["name"].as_str().unwrap())
+        .collect();
+    assert_eq!(names, ["Carol", "Alice"]);
+}
+
+#[test]
+fn export_since_skips_older_contacts() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    let cutoff = chrono::Utc::now().to_rfc3339();
+    std::thread::sleep(std::time::Duration::from_millis(20));
+    contacts_at(&db, &["add", "Bob", "bob@x.com"])
+        .assert()
+        .success();
+
+    let out = contacts_at(&db, &["export", "--format", "csv", "--since", &cutoff])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let csv = String::from_utf8(out.stdout).unwrap();
+    assert!(csv.contains("Bob"));
+    assert!(!csv.contains("Alice"));
+}
+
+#[test]
+fn list_pages_with_after_id() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    for name in ["Alice", "Bob", "Carol"] {
+        contacts_at(&db, &["add", name, "x@example.com"])
+            .assert()
+            .success();
+    }
+
+    let mut names = Vec::new();
+    let mut args = vec!["list".to_string(), "--limit".into(), "2".into()];
+    loop {
+        let out = contacts_at(&db, &args.iter().map(String::as_str).collect::<Vec<_>>())
+            .output()
+            .unwrap();
+        assert!(out.status.success());
+        let stdout = String::from_utf8(out.stdout).unwrap();
+        let mut next = None;
+        for line in stdout.lines() {
+            match line.strip_prefix("Next: --after-id ") {
+                Some(id) => next = Some(id.to_string()),
+                None => names.push(line.split(" | ").nth(1).unwrap().to_string()),
+            }
+        }
+        match next {
+            Some(id) => {
+                args = vec![
+                    "list".into(),
+                    "--limit".into(),
+                    "2".into(),
+                    "--after-id".into(),
+                    id,
+                ]
+            }
+            None => break,
+        }
+    }
+    names.sort();
+    assert_eq!(names, ["Alice", "Bob", "Carol"]);
+}
+
+#[test]
+fn add_and_remove_are_audited() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    let out = contacts_at(&db, &["list"]).output().unwrap();
+    let id = String::from_utf8(out.stdout).unwrap()[..36].to_string();
+    contacts_at(&db, &["remove", &id]).assert().success();
+
+    let log = std::fs::read_to_string(dir.path().join("contacts.json.audit.jsonl")).unwrap();
+    let entries: Vec<serde_json::Value> = log
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0]["operation"], "add");
+    assert_eq!(entries[1]["operation"], "remove");
+    assert!(entries.iter().all(|e| e["contact_id"] == id.as_str()));
+
+    contacts_at(&db, &["audit-log", "--tail", "1"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("remove").and(predicate::str::contains("add").not()));
+}
+
+#[test]
+fn export_template_is_a_header_row() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    let out = contacts_at(&db, &["export", "--format", "csv", "--template"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(
+        String::from_utf8(out.stdout).unwrap(),
+        "name,email,phone,tags,birthday,anniversary,timezone,company,\
+         street,city,state,postal_code,country\n"
+    );
+    contacts_at(&db, &["export", "--template"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn find_can_hide_phone_numbers() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    for (name, phone) in [("Ann Lee", "555-0101"), ("Bob Lee", "555-0102")] {
+        contacts_at(&db, &["add", name, "x@example.com", "--phone", phone])
+            .assert()
+            .success();
+    }
+    contacts_at(&db, &["find", "lee", "--exclude-fields", "phone"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Ann Lee\nBob Lee\nFound: 2"))
+        .stdout(predicate::str::contains("555").not());
+}
+
+#[test]
+fn export_group_by_company_writes_one_file_each() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    for (name, company) in [
+        ("Ann", "Acme Corp"),
+        ("Bob", "Other/Co"),
+        ("Cy", "Acme Corp"),
+    ] {
+        contacts_at(&db, &["add", name, "x@example.com", "--company", company])
+            .assert()
+            .success();
+    }
+    let exports = dir.path().join("exports");
+    contacts_at(
+        &db,
+        &[
+            "export",
+            "--format",
+            "csv",
+            "--group-by",
+            "company",
+            "--output-dir",
+        ],
+    )
+    .arg(&exports)
+    .assert()
+    .success();
+
+    let acme = std::fs::read_to_string(exports.join("Acme_Corp.csv")).unwrap();
+    let other = std::fs::read_to_string(exports.join("Other_Co.csv")).unwrap();
+    assert_eq!(std::fs::read_dir(&exports).unwrap().count(), 2);
+    assert!(acme.contains("Ann") && acme.contains("Cy") && !acme.contains("Bob"));
+    assert!(other.contains("Bob") && !other.contains("Ann"));
+}
+
+#[test]
+fn import_ignore_errors_keeps_valid_rows() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    let csv = dir.path().join("people.csv");
+    std::fs::write(&csv, "name,email\nAnn,ann@x.com\nBob,\nCy,cy@x.com\n").unwrap();
+
+    contacts_at(&db, &["import"])
+        .arg(&csv)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("row 2"));
+    contacts_at(&db, &["list"])
+        .assert()
+        .stdout(predicate::str::contains("Ann").not());
+
+    contacts_at(&db, &["import", "--ignore-errors"])
+        .arg(&csv)
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Imported 2"))
+        .stdout(predicate::str::contains("Failed 1:"));
+    contacts_at(&db, &["list"])
+        .assert()
+        .stdout(predicate::str::contains("Total: 2"));
+}
+
+#[test]
+fn list_sorts_by_creation_time() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    for name in ["Cy", "Bob", "Ann"] {
+        contacts_at(&db, &["add", name, "x@example.com"])
+            .assert()
+            .success();
+    }
+    let names = |args: &[&str]| -> Vec<String> {
+        let out = contacts_at(&db, args).output().unwrap();
+        String::from_utf8(out.stdout)
+            .unwrap()
+            .lines()
+            .filter_map(|l| l.split(" | ").nth(1))
+            .map(String::from)
+            .collect()
+    };
+    assert_eq!(
+        names(&["list", "--sort-by", "created_at"]),
+        ["Cy", "Bob", "Ann"]
+    );
+    assert_eq!(
+        names(&["list", "--sort-by", "created_at", "--reverse"]),
+        ["Ann", "Bob", "Cy"]
+    );
+    assert_eq!(names(&["list", "--sort-by", "name"]), ["Ann", "Bob", "Cy"]);
+}
+
+#[test]
+fn search_matches_find() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    contacts_at(&db, &["add", "Bob", "bob@x.com"])
+        .assert()
+        .success();
+
+    let find = contacts_at(&db, &["find", "alice"]).output().unwrap();
+    let search = contacts_at(&db, &["search", "--query", "alice"])
+        .output()
+        .unwrap();
+    assert!(search.status.success());
+    assert_eq!(search.stdout, find.stdout);
+    contacts_at(&db, &["search", "alice"]).assert().failure();
+}
+
+#[test]
+fn find_first_exits_on_first_match() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    contacts_at(&db, &["add", "Alicia", "alicia@x.com"])
+        .assert()
+        .success();
+
+    contacts_at(&db, &["find", "ali", "--first"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Alice"))
+        .stdout(predicate::str::contains("Alicia").not());
+    contacts_at(&db, &["find", "nobody", "--first"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("no contact matches 'nobody'"));
+}
+
+#[test]
+fn lint_warns_and_exits_one() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@example.com", "--tag", "work"])
+        .assert()
+        .success();
+    contacts_at(&db, &["lint", "--allowed-tags", "work,family"])
+        .assert()
+        .success()
+        .stdout("No issues found.\n");
+
+    contacts_at(&db, &["add", "42", "bad@@example", "--tag", "wrok"])
+        .assert()
+        .success();
+    contacts_at(&db, &["lint", "--allowed-tags", "work,family"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains(
+            "invalid email address 'bad@@example'",
+        ))
+        .stdout(predicate::str::contains("name is only digits"))
+        .stdout(predicate::str::contains(
+            "tag 'wrok' is not in the allowed list",
+        ))
+        .stderr(predicate::str::contains("3 issue(s) found"));
+}
+
+#[test]
+fn suggest_lists_contacts_at_the_same_domain() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    for (name, email) in [
+        ("Zoe", "zoe@acme.com"),
+        ("Bob", "bob@other.org"),
+        ("Amy", "amy@Acme.com"),
+    ] {
+        contacts_at(&db, &["add", name, email]).assert().success();
+    }
+
+    let out = contacts_at(&db, &["suggest", "--for", "newperson@acme.com"])
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    let stdout = String::from_utf8(out.stdout).unwrap();
+    let amy = stdout.find("Amy").unwrap();
+    let zoe = stdout.find("Zoe").unwrap();
+    assert!(amy < zoe);
+    assert!(!stdout.contains("Bob"));
+    assert!(stdout.ends_with("Suggestions: 2\n"));
+}
+
+#[test]
+fn resolve_looks_up_exact_email() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+
+    contacts_at(&db, &["resolve", "--email", "ALICE@x.com"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Alice"));
+    let out = contacts_at(&db, &["resolve", "--email", "alice@x.com", "--json"])
+        .output()
+        .unwrap();
+    let c: serde_json::Value = serde_json::from_slice(&out.stdout).unwrap();
+    assert_eq!(c["name"], "Alice");
+    contacts_at(&db, &["resolve", "--email", "alice@x"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("No contact with email alice@x"));
+}
+
+#[test]
+fn id_prefix_selects_a_unique_contact() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    // 17 random ids: at least two must share their first hex digit.
+    let lines: String = (0..17).map(|i| format!("add P{i} p{i}@x.com\n")).collect();
+    contacts_at(&db, &["batch"])
+        .write_stdin(lines)
+        .assert()
+        .success();
+    let json: serde_json::Value = serde_json::from_slice(&std::fs::read(&db).unwrap()).unwrap();
+    let ids: Vec<&str> = json
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| c["id"].as_str().unwrap())
+        .collect();
+
+    let unique = &ids[0][..ids[0].len() - 1];
+    contacts_at(&db, &["show", unique])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("P0"));
+
+    let shared = ids
+        .iter()
+        .map(|id| &id[..1])
+        .find(|p| ids.iter().filter(|id| id.starts_with(p)).count() > 1)
+        .unwrap();
+    contacts_at(&db, &["remove", shared])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("use more characters"));
+    contacts_at(&db, &["update", "zz", "--name", "X"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("No contact with id zz"));
+
+    for args in [
+        &["card", unique][..],
+        &["rename", unique, "Pat"],
+        &["archive", unique],
+        &["unarchive", unique],
+        &["tag", "vip", unique],
+    ] {
+        contacts_at(&db, args).assert().success();
+    }
+    contacts_at(&db, &["show", unique])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Pat").and(predicate::str::contains("vip")));
+
+    contacts_at(&db, &["remove", unique]).assert().success();
+    contacts_at(&db, &["show", unique]).assert().failure();
+    contacts_at(&db, &["unremove", unique])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("in the undo file"));
+    contacts_at(&db, &["unremove", ids[0]]).assert().success();
+}
+
+#[test]
+fn validate_exits_one_for_invalid_contacts() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    contacts_at(&db, &["validate"])
+        .assert()
+        .success()
+        .stdout("All 1 contacts are valid.\n");
+
+    let text = std::fs::read_to_string(&db).unwrap();
+    std::fs::write(&db, text.replace("alice@x.com", "alice\\u0007@x.com")).unwrap();
+    contacts_at(&db, &["--skip-verify", "validate"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("control characters"))
+        .stderr(predicate::str::contains("1 invalid contact(s)"));
+}
+
+#[test]
+fn add_with_id_keeps_the_given_uuid() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    let id = "3f2a9c1e-7b4d-4e2a-9f1c-0d8e6b5a4c3b";
+    contacts_at(&db, &["add", "Alice", "alice@x.com", "--with-id", id])
+        .assert()
+        .success();
+    contacts_at(&db, &["show", id])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("Alice"));
+
+    contacts_at(&db, &["add", "Bob", "bob@x.com", "--with-id", id])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("already in use"));
+    contacts_at(&db, &["add", "Bob", "bob@x.com", "--with-id", "not-a-uuid"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("not a valid UUID v4"));
+}
+
+#[test]
+fn export_compact_and_indent_control_json_layout() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    contacts_at(&db, &["add", "Bob", "bob@x.com"])
+        .assert()
+        .success();
+
+    let compact = contacts_at(&db, &["export", "--compact"]).output().unwrap();
+    let text = String::from_utf8(compact.stdout).unwrap();
+    assert_eq!(text.lines().count(), 1);
+    let rows: serde_json::Value = serde_json::from_str(&text).unwrap();
+    assert_eq!(rows.as_array().unwrap().len(), 2);
+
+    let wide = contacts_at(&db, &["export", "--indent", "4"])
+        .output()
+        .unwrap();
+    let text = String::from_utf8(wide.stdout).unwrap();
+    assert!(text.lines().any(|l| l.starts_with("    {")));
+    assert!(text.lines().any(|l| l.starts_with("        \"name\"")));
+
+    let default = contacts_at(&db, &["export", "--pretty"]).output().unwrap();
+    let text = String::from_utf8(default.stdout).unwrap();
+    assert!(text.lines().any(|l| l.starts_with("  {")));
+    contacts_at(&db, &["export", "--compact", "--format", "csv"])
+        .assert()
+        .failure();
+}
+
+#[test]
+fn compact_survives_later_batch_lines_and_stdin_files() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Alice alice@x.com\ncompact\nadd Bob bob@x.com\n")
+        .assert()
+        .success();
+    let text = std::fs::read_to_string(&db).unwrap();
+    assert!(!text.contains('\n'));
+    assert!(text.contains("Bob"));
+
+    let out = contacts()
+        .args(["--file", "-", "compact"])
+        .write_stdin(text)
+        .output()
+        .unwrap();
+    assert!(out.status.success());
+    assert_eq!(String::from_utf8(out.stdout).unwrap().lines().count(), 1);
+    contacts()
+        .args(["--file", "-", "compact", "--defrag"])
+        .write_stdin("[]")
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("needs a data file"));
+
+    let list = contacts_at(&db, &["list", "--no-color"]).output().unwrap();
+    let id = String::from_utf8(list.stdout).unwrap()[..36].to_string();
+    contacts_at(&db, &["archive", &id]).assert().success();
+    contacts_at(&db, &["compact", "--defrag"])
+        .assert()
+        .success();
+    contacts_at(&db, &["audit-log"])
+        .assert()
+        .stdout(predicate::str::contains("purged 1 archived contact(s)"));
+    contacts_at(&db, &["list", "--include-archived"])
+        .assert()
+        .stdout(predicate::str::contains("Total: 1"));
+}
+
+#[test]
+fn head_and_tail_follow_list_order() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Carol carol@x.com\nadd Alice alice@x.com\nadd Bob bob@x.com\n")
+        .assert()
+        .success();
+    let names = |args: &[&str]| -> Vec<String> {
+        let out = contacts_at(&db, args).output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stdout)
+            .unwrap()
+            .lines()
+            .map(|l| l.split(" | ").nth(1).unwrap().to_string())
+            .collect()
+    };
+    assert_eq!(
+        names(&["head", "-n", "2", "--sort-by", "name"]),
+        ["Alice", "Bob"]
+    );
+    assert_eq!(
+        names(&["tail", "-n", "1", "--sort-by", "name", "--reverse"]),
+        ["Alice"]
+    );
+
+    let list = contacts_at(&db, &["list", "--no-color"]).output().unwrap();
+    let carol = String::from_utf8(list.stdout).unwrap()[..36].to_string();
+    contacts_at(&db, &["archive", &carol]).assert().success();
+    assert_eq!(names(&["head", "-n", "1"]), ["Alice"]);
+    assert_eq!(names(&["head", "-n", "1", "--include-archived"]), ["Carol"]);
+    contacts_at(&db, &["tail", "-n", "1", "--output-separator", ";"])
+        .assert()
+        .stdout(predicate::str::contains(";Bob"));
+}
+
+#[test]
+fn invalid_ids_survive_saves_and_are_not_imported() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["add", "Alice", "alice@x.com"])
+        .assert()
+        .success();
+    let mut json: serde_json::Value = serde_json::from_slice(&std::fs::read(&db).unwrap()).unwrap();
+    let mut legacy = json[0].clone();
+    legacy["id"] = "legacy-42".into();
+    legacy["name"] = "Legacy".into();
+    legacy["emails"] = serde_json::json!(["legacy@x.com"]);
+
+    // A hand edit: no checksum to go with it.
+    json.as_array_mut().unwrap().push(legacy.clone());
+    std::fs::write(&db, serde_json::to_vec(&json).unwrap()).unwrap();
+    std::fs::remove_file(dir.path().join("contacts.json.sha256")).unwrap();
+    contacts_at(&db, &["add", "Bob", "b@x.com"])
+        .assert()
+        .success()
+        .stderr(predicate::str::contains("invalid id 'legacy-42'"));
+    let saved = std::fs::read_to_string(&db).unwrap();
+    assert!(saved.contains("legacy-42") && saved.contains("Legacy"));
+    assert!(saved.contains("Bob"));
+
+    let import = dir.path().join("import.json");
+    legacy["id"] = "legacy-43".into();
+    std::fs::write(&import, serde_json::to_vec(&[legacy]).unwrap()).unwrap();
+    contacts_at(&db, &["import"])
+        .arg(&import)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid contact id 'legacy-43'"));
+
+    let patch = dir.path().join("patch.json");
+    std::fs::write(
+        &patch,
+        r#"[{"op": "replace", "path": "/0/id", "value": "alice"}]"#,
+    )
+    .unwrap();
+    contacts_at(&db, &["patch"])
+        .arg(&patch)
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("invalid contact id 'alice'"));
+    assert!(!std::fs::read_to_string(&db).unwrap().contains("legacy-43"));
+}
+
+#[test]
+fn pipeline_shares_one_store() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(
+        &db,
+        &[
+            "pipeline",
+            "--cmd",
+            "add Alice alice@x.com",
+            "--cmd",
+            "add \"Bob Work\" bob@work.com",
+            "--cmd",
+            "find alice",
+        ],
+    )
+    .assert()
+    .success()
+    .stdout(predicate::str::contains("Alice - No phone\nFound: 1"))
+    .stdout(predicate::str::contains("Saved."));
+
+    contacts_at(
+        &db,
+        &["pipeline", "--cmd", "find work", "--cmd", "archive <all>"],
+    )
+    .assert()
+    .success();
+    contacts_at(&db, &["list", "--no-color"])
+        .assert()
+        .stdout(predicate::str::contains("Bob").not())
+        .stdout(predicate::str::contains("Total: 1"));
+
+    // A failing step leaves the file untouched.
+    contacts_at(
+        &db,
+        &[
+            "pipeline",
+            "--cmd",
+            "add Carol carol@x.com",
+            "--cmd",
+            "remove nope",
+        ],
+    )
+    .assert()
+    .failure();
+    contacts_at(&db, &["find", "carol"])
+        .assert()
+        .stdout("Found: 0\n");
+}
+
+#[test]
+fn version_command_prints_package_version() {
+    contacts()
+        .args(["version", "--no-color"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains(format!(
+            "Version:     {}",
+            env!("CARGO_PKG_VERSION")
+        )))
+        .stdout(predicate::str::contains("Git commit:"));
+}
+
+#[test]
+fn reindex_reports_contact_count() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Alice alice@x.com\nadd Bob bob@x.com\n")
+        .assert()
+        .success();
+    contacts_at(&db, &["reindex"])
+        .assert()
+        .success()
+        .stdout("Index rebuilt: 2 contacts indexed\n");
+}
+
+#[test]
+fn find_by_tags() {
+    let dir = tempdir().unwrap();
+    let db = dir.path().join("contacts.json");
+    contacts_at(&db, &["batch"])
+        .write_stdin("add Wendy w@x.com --tag work --tag vip\nadd Pat p@x.com --tag personal\n")
+        .assert()
+        .success();
+    let find = |args: &[&str]| {
+        let mut all = vec!["find"];
+        all.extend_from_slice(args);
+        let out = contacts_at(&db, &all).output().unwrap();
+        assert!(out.status.success());
+        String::from_utf8(out.stdout).unwrap()
+    };
+    assert!(find(&["--all-tags", "work,vip"]).contains("Wendy"));
+    assert!(find(&["--any-tags", "vip,personal"]).contains("Found: 2"));
+    assert_eq!(find(&["--all-tags", "work,personal"]), "Found: 0\n");
+    assert_eq!(
+        find(&["pat", "--any-tags", "vip,personal"]).lines().count(),
+        2
+    );
+    contacts_at(&db, &["find"]).assert().failure();
+}